@@ -0,0 +1,60 @@
+// Compares the selection-based maths::median against the sort-based version it replaced.
+// libtest's #[bench] is nightly only, which is fine since rust-toolchain.toml pins nightly.
+//
+// $ cargo bench --bench median
+#![feature(test)]
+
+extern crate test;
+
+use rust_playing::maths;
+use test::{black_box, Bencher};
+
+const LEN: usize = 1_000_000;
+
+// Pseudo-random, just so the input isn't already sorted.
+fn samples(len: usize) -> Vec<i32> {
+    let mut rng = maths::Rng::new(1);
+    (0..len).map(|_| rng.next_u64() as i32).collect()
+}
+
+// The old maths::median: copy, fully sort, read the middle.
+fn median_by_sort(v: &[i32]) -> Option<f64> {
+    let mut v = v.to_vec();
+    v.sort();
+
+    match v.len() % 2 {
+        0 => {
+            let upper_index = v.len() / 2;
+            let upper_val = *v.get(upper_index)? as f64;
+            let lower_val = *v.get(upper_index - 1)? as f64;
+            Some((upper_val + lower_val) / 2.0)
+        }
+        1 => Some(v[v.len() / 2] as f64),
+        _ => None,
+    }
+}
+
+#[bench]
+fn median_sort(b: &mut Bencher) {
+    let v = samples(LEN);
+    b.iter(|| median_by_sort(black_box(&v)));
+}
+
+#[bench]
+fn median_select(b: &mut Bencher) {
+    let v = samples(LEN);
+    b.iter(|| maths::median(black_box(&v)));
+}
+
+// The slice is reset every iteration, otherwise later iterations would select on a slice that's
+// already partitioned around the middle. That reset costs about as much as the copy inside
+// maths::median, so this mostly shows the cost of selection itself.
+#[bench]
+fn median_in_place_select(b: &mut Bencher) {
+    let v = samples(LEN);
+    let mut scratch = v.clone();
+    b.iter(|| {
+        scratch.copy_from_slice(&v);
+        maths::median_in_place(black_box(&mut scratch))
+    });
+}
@@ -1,5 +1,3 @@
-#![feature(vec_into_raw_parts)]
-
 fn main() {
     let s = String::from("Hello, world!");
     let word = first_word(&s);
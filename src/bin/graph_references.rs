@@ -30,20 +30,20 @@ mod graph {
             Node { value, children }
         }
 
+        #[allow(dead_code)]
         pub fn add_child(&mut self, child: NodeRef<T>) {
             self.children.push(child);
         }
 
+        #[allow(dead_code)]
         pub fn has_descendant(&self, node: &NodeRef<T>) -> bool {
             if self.children.contains(node) {
                 return true;
             }
 
-            return self
-                .children
+            self.children
                 .iter()
-                .find(|child| child.borrow().has_descendant(node))
-                .is_some();
+                .any(|child| child.borrow().has_descendant(node))
         }
     }
 }
@@ -90,7 +90,7 @@ fn make_graph() -> Vec<Node<i32>> {
         Rc::strong_count(&c)
     );
 
-    return vec![d, e, f];
+    vec![d, e, f]
 }
 
 fn main() {
@@ -104,7 +104,7 @@ fn main() {
                 "Node {}: strong count for child {:?}: {:?}",
                 node.value,
                 child,
-                Rc::strong_count(&child)
+                Rc::strong_count(child)
             );
         }
     }
@@ -0,0 +1,290 @@
+// Times every maths algorithm on a few input sizes and distributions, and prints one table per
+// size with the median and standard deviation of the runs. Unlike benches/, this only needs
+// std::time::Instant, so it works on any toolchain and in one go.
+//
+// Build with --release, otherwise it's mostly timing debug assertions. The sizes can be given as
+// arguments:
+//
+// $ cargo run --release --bin maths_bench
+// $ cargo run --release --bin maths_bench -- 1000 10000000
+use std::hint::black_box;
+use std::time::Instant;
+
+use rust_playing::maths;
+use rust_playing::maths::{
+    Binning, CountMinSketch, FrequencyTable, Histogram, Interpolation, KllSketch, MisraGries,
+    NanPolicy, OutlierMethod, Rng, RunningMedian, SlidingMedian,
+};
+
+const DEFAULT_SIZES: [usize; 3] = [1_000, 100_000, 1_000_000];
+
+// Runs thrown away first, so caches are warm and the allocator has settled, then the runs that
+// count.
+const WARM_UP_RUNS: usize = 2;
+const RUNS: usize = 10;
+
+#[derive(Debug, Clone, Copy)]
+enum Distribution {
+    // Values spread evenly over len / 10 possible values, so each shows up about 10 times.
+    Uniform,
+    // Small values far more likely than big ones (cubing a uniform fraction), so there's a clear
+    // mode and a long tail.
+    Skewed,
+    // A shuffled 0..len. Worst case for anything that keeps one entry per distinct value.
+    AllUnique,
+    AllEqual,
+}
+
+const DISTRIBUTIONS: [Distribution; 4] = [
+    Distribution::Uniform,
+    Distribution::Skewed,
+    Distribution::AllUnique,
+    Distribution::AllEqual,
+];
+
+impl Distribution {
+    fn samples(self, len: usize) -> Vec<i32> {
+        // Always the same seed, so every run of the binary times the same input.
+        let mut rng = Rng::new(0x9E37_79B9);
+        let distinct = (len / 10).max(1) as f64;
+        match self {
+            Distribution::Uniform => (0..len)
+                .map(|_| (rng.next_f64() * distinct) as i32)
+                .collect(),
+            Distribution::Skewed => (0..len)
+                .map(|_| (rng.next_f64().powi(3) * distinct) as i32)
+                .collect(),
+            Distribution::AllUnique => {
+                let mut v: Vec<i32> = (0..len as i32).collect();
+                for i in (1..v.len()).rev() {
+                    v.swap(i, rng.below(i + 1));
+                }
+                v
+            }
+            Distribution::AllEqual => vec![7; len],
+        }
+    }
+}
+
+// A name for the table and the work to time.
+type Algorithm = (&'static str, fn(&[i32]));
+
+// `v` as (value, weight) pairs for the weighted functions, with weights cycling through 1, 2 and
+// 3. Building them is part of what's timed, but it's a single pass next to their sorting or
+// counting.
+fn weighted(v: &[i32]) -> Vec<(i32, usize)> {
+    v.iter().enumerate().map(|(i, &x)| (x, 1 + i % 3)).collect()
+}
+
+// The two halves of `v`, as the x and y of the correlation functions.
+fn halves(v: &[i32]) -> (&[i32], &[i32]) {
+    let half = v.len() / 2;
+    (&v[..half], &v[half..2 * half])
+}
+
+// Results are passed through black_box so the work can't be optimized away.
+fn algorithms() -> Vec<Algorithm> {
+    vec![
+        ("mode_n", |v| {
+            black_box(maths::mode_n(v)).ok();
+        }),
+        ("mode_n_lgn", |v| {
+            black_box(maths::mode_n_lgn(v)).ok();
+        }),
+        ("mode_n_with (Skip)", |v| {
+            black_box(maths::mode_n_with(v, NanPolicy::Skip)).ok();
+        }),
+        ("mode_n_lgn_with (Skip)", |v| {
+            black_box(maths::mode_n_lgn_with(v, NanPolicy::Skip)).ok();
+        }),
+        ("par_mode", |v| {
+            black_box(maths::par_mode(v, maths::available_threads())).ok();
+        }),
+        ("MisraGries (k = 100)", |v| {
+            let mut mg = MisraGries::new(100);
+            v.iter().for_each(|&x| mg.add(x));
+            black_box(mg.mode()).ok();
+        }),
+        ("CountMinSketch (1%)", |v| {
+            let mut cms = CountMinSketch::with_error(0.01, 0.01, 10).unwrap();
+            v.iter().for_each(|&x| cms.add(x));
+            black_box(cms.mode()).ok();
+        }),
+        ("median", |v| {
+            black_box(maths::median(v)).ok();
+        }),
+        ("median_exact", |v| {
+            black_box(maths::median_exact(v)).ok();
+        }),
+        ("RunningMedian", |v| {
+            let mut running = RunningMedian::new();
+            v.iter().for_each(|&x| running.push(x));
+            black_box(running.median()).ok();
+        }),
+        ("SlidingMedian (101)", |v| {
+            let mut sliding = SlidingMedian::new(101);
+            for &x in v {
+                sliding.push(x);
+                black_box(sliding.median()).ok();
+            }
+        }),
+        ("KllSketch median", |v| {
+            let mut sketch = KllSketch::default();
+            sketch.extend(v.iter().copied());
+            black_box(sketch.quantile(0.5)).ok();
+        }),
+        ("quantiles (3)", |v| {
+            black_box(maths::quantiles(
+                v,
+                &[0.5, 0.9, 0.99],
+                Interpolation::Linear,
+            ))
+            .ok();
+        }),
+        ("describe", |v| {
+            black_box(maths::describe(v)).ok();
+        }),
+        ("par_describe", |v| {
+            black_box(maths::par_describe(v, maths::available_threads())).ok();
+        }),
+        ("weighted_mean", |v| {
+            black_box(maths::weighted_mean(&weighted(v))).ok();
+        }),
+        ("weighted_median", |v| {
+            black_box(maths::weighted_median(&weighted(v))).ok();
+        }),
+        ("weighted_mode", |v| {
+            black_box(maths::weighted_mode(&weighted(v))).ok();
+        }),
+        ("pearson_correlation", |v| {
+            let (x, y) = halves(v);
+            black_box(maths::pearson_correlation(x, y)).ok();
+        }),
+        ("spearman_correlation", |v| {
+            let (x, y) = halves(v);
+            black_box(maths::spearman_correlation(x, y)).ok();
+        }),
+        ("linear_regression", |v| {
+            let (x, y) = halves(v);
+            black_box(maths::linear_regression(x, y)).ok();
+        }),
+        ("moving_average (100)", |v| {
+            black_box(maths::moving_average(v, 100).last());
+        }),
+        ("weighted_moving_average (3)", |v| {
+            black_box(maths::weighted_moving_average(v, &[1.0, 2.0, 3.0]).last());
+        }),
+        ("exponential_moving_average", |v| {
+            black_box(maths::exponential_moving_average(v, 0.1).last());
+        }),
+        ("cumulative_sum", |v| {
+            black_box(maths::cumulative_sum(v).last());
+        }),
+        ("median_absolute_deviation", |v| {
+            black_box(maths::median_absolute_deviation(v)).ok();
+        }),
+        ("outliers (IQR)", |v| {
+            black_box(maths::outliers(v, OutlierMethod::Iqr(1.5))).ok();
+        }),
+        ("outliers (MAD)", |v| {
+            black_box(maths::outliers(v, OutlierMethod::Mad(3.5))).ok();
+        }),
+        ("trimmed_mean (10%)", |v| {
+            black_box(maths::trimmed_mean(v, 0.1)).ok();
+        }),
+        ("winsorize (10%)", |v| {
+            black_box(maths::winsorize(v, 0.1)).ok();
+        }),
+        ("bootstrap (mean, 10)", |v| {
+            black_box(maths::bootstrap(v, maths::mean, 10, 0.95, 1)).ok();
+        }),
+        ("FrequencyTable", |v| {
+            black_box(FrequencyTable::new(v));
+        }),
+        ("Histogram (Sturges)", |v| {
+            black_box(Histogram::new(v, Binning::Sturges)).ok();
+        }),
+    ]
+}
+
+// Seconds per run.
+fn time_runs(f: fn(&[i32]), v: &[i32]) -> Vec<f64> {
+    for _ in 0..WARM_UP_RUNS {
+        f(black_box(v));
+    }
+
+    (0..RUNS)
+        .map(|_| {
+            let start = Instant::now();
+            f(black_box(v));
+            start.elapsed().as_secs_f64()
+        })
+        .collect()
+}
+
+fn format_seconds(seconds: f64) -> String {
+    match seconds {
+        s if s < 1e-6 => format!("{:.0} ns", s * 1e9),
+        s if s < 1e-3 => format!("{:.1} µs", s * 1e6),
+        s if s < 1.0 => format!("{:.1} ms", s * 1e3),
+        s => format!("{s:.2} s"),
+    }
+}
+
+// "median ± stddev". The median, not the mean, so one run that got descheduled doesn't skew it.
+fn format_times(times: &[f64]) -> String {
+    let median = maths::median(times).unwrap_or(f64::NAN);
+    let stddev = maths::sample_stddev(times).unwrap_or(0.0);
+    format!("{} ± {}", format_seconds(median), format_seconds(stddev))
+}
+
+fn main() {
+    let sizes: Vec<usize> = std::env::args()
+        .skip(1)
+        .map(|arg| arg.parse().expect("sizes must be whole numbers"))
+        .collect();
+    let sizes = if sizes.is_empty() {
+        DEFAULT_SIZES.to_vec()
+    } else {
+        sizes
+    };
+
+    if cfg!(debug_assertions) {
+        println!("Debug build: timings will be slow. Use cargo run --release.\n");
+    }
+    println!(
+        "{RUNS} runs after {WARM_UP_RUNS} warm-up runs, median ± sample stddev. {} threads for par_*.",
+        maths::available_threads()
+    );
+
+    let algorithms = algorithms();
+    let name_width = algorithms
+        .iter()
+        .map(|(name, _)| name.len())
+        .max()
+        .unwrap_or(0);
+    let column_width = 22;
+
+    for len in sizes {
+        let inputs: Vec<Vec<i32>> = DISTRIBUTIONS.iter().map(|d| d.samples(len)).collect();
+
+        println!("\nn = {len}");
+        print!("{:name_width$}", "");
+        for distribution in DISTRIBUTIONS {
+            print!(" | {:>column_width$}", format!("{distribution:?}"));
+        }
+        println!();
+        println!(
+            "{}",
+            "-".repeat(name_width + DISTRIBUTIONS.len() * (column_width + 3))
+        );
+
+        for &(name, f) in &algorithms {
+            print!("{name:name_width$}");
+            for v in &inputs {
+                print!(" | {:>column_width$}", format_times(&time_runs(f, v)));
+            }
+            println!();
+        }
+    }
+}
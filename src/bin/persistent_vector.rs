@@ -0,0 +1,20 @@
+use rust_playing::persistent_vector::PersistentVector;
+
+// $ cargo run --bin persistent_vector
+
+fn main() {
+    let v1: PersistentVector<i32> = (0..100).collect();
+    let v2 = v1.set(10, -10).unwrap();
+    let v3 = v2.push_back(100);
+
+    // Each version is still intact after the later ones were made from it.
+    println!("v1[10] = {:?}, len = {}", v1.get(10), v1.len());
+    println!("v2[10] = {:?}, len = {}", v2.get(10), v2.len());
+    println!(
+        "v3[10] = {:?}, v3[100] = {:?}, len = {}",
+        v3.get(10),
+        v3.get(100),
+        v3.len()
+    );
+    println!("v3 = {v3:?}");
+}
@@ -1,100 +1,133 @@
-// My goal was to "alias" the Rc<RefCell<i32>> original node type into
-// something easier to understand and read.
-// Does wrapping Rc<RefCell<T>> in a tuple struct cause the reference counting
-// to break down? I.e., does Rc have to be the outermost type?
-#[derive(Debug)]
-enum List {
-    Cons(Node<i32>, Rc<List>),
-    Nil,
-}
-
-// Newtype pattern
-// https://doc.rust-lang.org/book/ch19-04-advanced-types.html
-#[derive(Debug)]
-struct Node<T>(Rc<RefCell<T>>);
-
-impl<T> Node<T> {
-    fn new(i: T) -> Node<T> {
-        Node(Rc::new(RefCell::new(i)))
-    }
-
-    // Is this the right implementation of clone? On second look, I think it is not because
-    // we are only adding a reference to the inner data wrapped by this Node, but clone()
-    // should return an entire new copy to the caller... I.e., yes we get a new Node wrapper, but
-    // not a new clone of the underlying data. It's find with immutable data like here (with Rc),
-    // but still.
-    fn clone(&self) -> Self {
-        Node(Rc::clone(&self.0))
-    }
-}
-
-impl<T> Deref for Node<T> {
-    type Target = RefCell<T>;
-
-    fn deref(&self) -> &Self::Target {
-        &self.0
-    }
-}
-
-use crate::List::{Cons, Nil};
-use std::cell::RefCell;
+// The List, Node and Zipper types live in src/ref_cells.rs so ref_cells_original can share them.
+use rust_playing::ref_cells::List::{Cons, Nil};
+use rust_playing::ref_cells::{node_narrative, CowList, CowNode, Node, Zipper};
+use rust_playing::weak_cache::WeakCache;
 use std::mem::drop;
-use std::ops::Deref;
 use std::rc::Rc;
 
 fn main() {
-    let shared_node = Node::new(5);
+    let counts = node_narrative(|name, list| println!("{name} after = {list:?}"));
 
-    let a = Rc::new(Cons(Node::clone(&shared_node), Rc::new(Nil)));
-    let aa = Rc::new(Cons(Node::clone(&shared_node), Rc::clone(&a)));
-
-    let b = Cons(Node::new(3), Rc::clone(&aa));
-    let c = Cons(Node::new(4), Rc::clone(&aa));
+    println!("Reference count of a = {}", counts.a);
+    println!("Reference count of aa = {}", counts.aa);
+    println!("Reference count of shared_node = {}", counts.shared_node);
+    println!(
+        "Reference count of aa after dropping b and c = {}",
+        counts.aa_after_dropping_b_and_c
+    );
+    println!(
+        "Reference count of shared_node remains unchanged after dropping a = {}",
+        counts.shared_node_after_dropping_a
+    );
+    println!(
+        "Reference count of shared_node drops by 2 after dropping aa = {}",
+        counts.shared_node_after_dropping_aa
+    );
+    println!();
 
-    *shared_node.borrow_mut() += 10;
+    zipper_playing();
+    println!();
 
-    println!("a after = {a:?}");
-    println!("aa after = {aa:?}");
-    println!("b after = {b:?}");
-    println!("c after = {c:?}");
+    weak_cache_playing();
+    println!();
 
-    // a is referenced once by its variable and once by aa.
-    // aa is reference by its variable, b, and c.
-    println!("Reference count of a = {}", Rc::strong_count(&a));
-    println!("Reference count of aa = {}", Rc::strong_count(&aa));
+    cow_playing();
+}
 
-    // shared_node is referenced once by its variable, a, and aa.
+fn zipper_playing() {
+    let list = Rc::new(Cons(
+        Node::new(1),
+        Rc::new(Cons(
+            Node::new(2),
+            Rc::new(Cons(
+                Node::new(3),
+                Rc::new(Cons(Node::new(4), Rc::new(Nil))),
+            )),
+        )),
+    ));
+
+    // Walk to the 2, swap it for 20, and put a 15 in front of it.
+    let mut zipper = Zipper::new(&list);
+    zipper.right();
+    let old = zipper.replace(Node::new(20));
+    println!("Replaced {old:?}");
+    zipper.insert(Node::new(15));
+    println!("Focus after insert = {:?}", zipper.focus());
+
+    // Step back to the 1 and drop it.
+    zipper.left();
+    let deleted = zipper.delete();
+    println!("Deleted {deleted:?}");
+
+    let edited = zipper.into_list();
+    println!("list = {list:?}");
+    println!("edited = {edited:?}");
+
+    // The [3, 4] tail was never touched, so both lists point at the same Rc.
+    let (Cons(_, list_tail), Cons(_, edited_tail)) = (&*list, &*edited) else {
+        return;
+    };
+    let (Cons(_, list_tail), Cons(_, edited_tail)) = (&**list_tail, &**edited_tail) else {
+        return;
+    };
     println!(
-        "Reference count of shared_node = {}",
-        Rc::strong_count(&shared_node.0)
+        "Reference count of the shared [3, 4] tail = {} (same Rc: {})",
+        Rc::strong_count(list_tail),
+        Rc::ptr_eq(list_tail, edited_tail)
     );
+}
+
+fn weak_cache_playing() {
+    let mut cache = WeakCache::new();
+    let five = cache.insert("five", 5);
+    let also_five = cache.get(&"five").unwrap();
 
-    // This should remove 2 references to aa. New total = 1;
-    drop(b);
-    drop(c);
+    // The cache only holds a Weak reference, so it doesn't show up in the strong count.
     println!(
-        "Reference count of aa after dropping b and c = {}",
-        Rc::strong_count(&aa)
+        "Strong count of five = {}, weak count = {}",
+        Rc::strong_count(&five.0),
+        Rc::weak_count(&five.0)
     );
 
-    // Dropping `a` here doesn't affect the reference count of shared_node
-    // because the list that the variable `a` pointed to is still referenced
-    // by aa (a's ref count = 1 now) and therefore it's not dropped yet. Since
-    // `a` still ~exists, shared_node is still referenced by its own variable,
-    // the list that `a` used to point to, and the list that aa points to
-    // (still 3).
-    drop(a);
+    // The value stays cached while anyone still holds a handle to it...
+    drop(five);
+    println!("five after dropping one handle = {:?}", cache.get(&"five"));
+
+    // ...and disappears once the last handle is gone.
+    drop(also_five);
     println!(
-        "Reference count of shared_node remains unchanged after dropping a = {}",
-        Rc::strong_count(&shared_node.0)
+        "five after dropping both handles = {:?}",
+        cache.get(&"five")
     );
+}
+
+// The same `+= 10` on a node shared by two lists, once with Node and once with CowNode.
+fn cow_playing() {
+    let shared_node = Node::new(5);
+    let a = Cons(Node::clone(&shared_node), Rc::new(Nil));
+    let b = Cons(Node::clone(&shared_node), Rc::new(Nil));
 
-    // Dropping aa will removed two references to the shared node: one from
-    // aa itself and one from the ghost of `a`. The ghost of a is now cleaned
-    // up because its reference count moves to 0 when aa is dropped.
-    drop(aa);
+    // Every list shares the one RefCell, so both lists see 15.
+    *shared_node.borrow_mut() += 10;
+    println!("Node: a = {a:?}");
+    println!("Node: b = {b:?}");
+
+    let mut shared_cow = CowNode::new(5);
+    let cow_a = CowList::Cons(CowNode::clone(&shared_cow), Rc::new(CowList::Nil));
+    let cow_b = CowList::Cons(CowNode::clone(&shared_cow), Rc::new(CowList::Nil));
+
+    // The value is shared three ways, so make_mut() gives shared_cow its own copy first. Both
+    // lists still see 5.
+    *shared_cow.make_mut() += 10;
+    println!("CowNode: shared_cow = {shared_cow:?}");
+    println!("CowNode: cow_a = {cow_a:?}");
+    println!("CowNode: cow_b = {cow_b:?}");
     println!(
-        "Reference count of shared_node drops by 2 after dropping aa = {}",
-        Rc::strong_count(&shared_node.0)
+        "Reference count of shared_cow after make_mut = {}",
+        Rc::strong_count(&shared_cow.0)
     );
+
+    // Now shared_cow is the only handle to its copy, so this mutates in place.
+    *shared_cow.make_mut() += 10;
+    println!("CowNode: shared_cow after a second make_mut = {shared_cow:?}");
 }
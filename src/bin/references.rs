@@ -1,6 +1,3 @@
-// This file must also be compiled with the nightly channel to use this new API.
-#![feature(vec_into_raw_parts)]
-
 use std::mem;
 
 // $ cargo run --bin references
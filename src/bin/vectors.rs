@@ -1,68 +1,8 @@
-mod maths {
-    use std::collections::{HashMap, HashSet};
-
-    fn count_values(v: &Vec<i32>) -> HashMap<&i32, i32> {
-        let mut value_counts = HashMap::new();
-        for val in v {
-            let count = value_counts.entry(val).or_insert(0);
-            *count += 1;
-        }
-
-        value_counts
-    }
-
-    pub fn mode_n_lgn(v: &Vec<i32>) -> Option<HashSet<i32>> {
-        let mut value_counts: Vec<(i32, i32)> =
-            count_values(v).iter().map(|(&&k, &v)| (k, v)).collect();
-        value_counts.sort_by(|a, b| b.1.cmp(&a.1));
-
-        let most_freq_item = value_counts.first()?;
-        Some(HashSet::from_iter(
-            value_counts
-                .iter()
-                .take_while(|x| x.1 == most_freq_item.1)
-                .map(|&(item, _)| item),
-        ))
-    }
-
-    pub fn mode_n(v: &Vec<i32>) -> Option<HashSet<i32>> {
-        let value_counts = count_values(v);
-        let (_, &most_freq_count) = value_counts.iter().max_by_key(|(_, &count)| count)?;
-
-        Some(HashSet::from_iter(
-            value_counts
-                .iter()
-                .filter(|(_, &count)| count == most_freq_count)
-                .map(|(&&item, _)| item),
-        ))
-    }
-
-    pub fn median(v: &Vec<i32>) -> Option<f32> {
-        println!("Median of {v:?}");
-
-        let mut v: Vec<i32> = v.clone();
-        v.sort();
-
-        match v.len() % 2 {
-            0 => {
-                println!("vec has even number of elements");
-
-                // Using the (?) operator on Option<T> short circuits and propagates None if a value is not present.
-                // https://doc.rust-lang.org/reference/expressions/operator-expr.html#the-question-mark-operator
-                let upper_index = v.len() / 2;
-                let upper_val = *v.get(upper_index)? as f32;
-                let lower_val = *v.get(upper_index - 1)? as f32;
-
-                println!("v.get(upper_index - 1) = {:?}", upper_val);
-                println!("v.get(upper_index) = {:?}", lower_val);
-
-                Some((upper_val + lower_val) / 2.0)
-            }
-            1 => Some(v[v.len() / 2] as f32), // Integer division
-            _ => None,
-        }
-    }
-}
+// The maths module lives in src/maths/ so other binaries can use it too.
+use rust_playing::maths;
+use rust_playing::maths::{
+    Binning, FrequencyTable, Histogram, Interpolation, NanPolicy, OutlierMethod,
+};
 
 fn main() {
     let v1 = vec![1, 2, 3, 3, 4, 2, 3];
@@ -83,80 +23,86 @@ fn main() {
 
         let median = maths::median(&v);
         println!("Median = {median:?}");
-        println!();
-    }
-}
-
-#[cfg(test)]
-mod tests {
-    use std::collections::HashSet;
 
-    use super::*;
+        let median = maths::median_exact(&v).map(|m| m.to_string());
+        println!("Median (exact) = {median:?}");
 
-    #[test]
-    fn mode_n_works_one_mode() {
-        let v = vec![1, -2, 3, 3, 4, -2, 3];
+        let percentiles = maths::quantiles(&v, &[0.25, 0.5, 0.9], Interpolation::Linear);
+        println!("p25, p50, p90 = {percentiles:?}");
 
-        assert_eq!(maths::mode_n(&v).unwrap(), HashSet::from([3]));
-    }
-
-    #[test]
-    fn mode_n_works_many_modes() {
-        let v = vec![1, 2, 3, 4, 5];
-
-        assert_eq!(
-            maths::mode_n(&v).unwrap(),
-            HashSet::from_iter(v.into_iter())
-        );
-    }
-
-    #[test]
-    fn mode_n_returns_none_with_empty_vector() {
-        assert_eq!(maths::mode_n(&Vec::new()), None);
-    }
-
-    #[test]
-    fn median_works_with_odd_num_elements() {
-        let v = vec![3, 2, 2, 2, 1];
-        let expected = 2.0;
-
-        assert_eq!(maths::median(&v).unwrap(), expected);
-    }
-
-    #[test]
-    fn median_works_with_even_num_elements_same_middle_values() {
-        let v = vec![3, 2, 2, 1];
-        let expected = 2.0;
-
-        assert_eq!(maths::median(&v).unwrap(), expected);
-    }
-
-    #[test]
-    fn median_works_with_even_num_elements_different_middle_values() {
-        let v = vec![4, 3, 2, 1];
-        let expected = 2.5;
-
-        assert_eq!(maths::median(&v).unwrap(), expected);
+        let summary = maths::describe(&v);
+        println!("Summary = {summary:?}");
+        println!();
     }
 
-    #[test]
-    fn median_works_with_one_element_vector() {
-        let v = vec![1];
-        let expected = 1.0;
-
-        assert_eq!(maths::median(&v).unwrap(), expected);
+    // The same functions work on other numeric types.
+    let counters: Vec<u64> = vec![3, 1, 4, 1, 5];
+    println!("Vec {counters:?}");
+    println!("Mode (O(n)) = {:?}", maths::mode_n(&counters));
+    println!("Median = {:?}", maths::median(&counters));
+    println!();
+
+    let measurements = vec![0.5, 2.25, -1.0, 3.5];
+    println!("Vec {measurements:?}");
+    println!("Median = {:?}", maths::median(&measurements));
+    println!();
+
+    // Empty input is Err(Empty); bad input gets its own error. Measurements with gaps can skip
+    // the NaNs instead.
+    let readings = vec![0.5, f64::NAN, 2.25, 0.5, f64::NAN];
+    println!("Vec {readings:?}");
+    if let Err(e) = maths::median(&readings) {
+        println!("Median failed: {e}");
     }
-
-    #[test]
-    fn median_works_with_two_element_vector() {
-        let v = vec![1, 10];
-        let expected = 5.5;
-
-        assert_eq!(maths::median(&v).unwrap(), expected);
+    for nan in [NanPolicy::Skip, NanPolicy::Propagate] {
+        println!(
+            "Median ({nan:?}) = {:?}",
+            maths::median_with(&readings, nan)
+        );
+        println!("Mode ({nan:?}) = {:?}", maths::mode_n_with(&readings, nan));
     }
-
-    #[test]
-    fn median_returns_none_with_empty_vector() {
-        assert_eq!(maths::median(&Vec::new()), None);
+    println!();
+
+    let rolls = vec![1, 2, 3, 3, 4, 2, 3, 4, 4, 6, 5, 3];
+    println!("Frequencies of {rolls:?}");
+    print!("{}", FrequencyTable::new(&rolls));
+    println!();
+
+    // The same rolls, already counted.
+    let counted = [(1, 1), (2, 2), (3, 4), (4, 3), (5, 1), (6, 1)];
+    println!("Counted {counted:?}");
+    println!("Mean = {:?}", maths::weighted_mean(&counted));
+    println!("Median = {:?}", maths::weighted_median(&counted));
+    println!("Mode = {:?}", maths::weighted_mode(&counted));
+    println!();
+
+    // Requests per minute, smoothed.
+    let per_minute = [12, 15, 11, 40, 14, 13, 16, 12];
+    println!("Per minute {per_minute:?}");
+    let averages: Vec<f64> = maths::moving_average(&per_minute, 3).collect();
+    println!("3-minute moving average = {averages:?}");
+    let averages: Vec<f64> = maths::exponential_moving_average(&per_minute, 0.5).collect();
+    println!("EMA (alpha 0.5) = {averages:?}");
+    let totals: Vec<_> = maths::cumulative_sum(&per_minute).collect();
+    println!("Running total = {totals:?}");
+    println!(
+        "Outliers (IQR) = {:?}",
+        maths::outliers(&per_minute, OutlierMethod::Iqr(1.5))
+    );
+    println!(
+        "12.5% trimmed mean = {:?}",
+        maths::trimmed_mean(&per_minute, 0.125)
+    );
+    println!("Winsorized = {:?}", maths::winsorize(&per_minute, 0.125));
+    println!(
+        "Median with 95% bootstrap interval = {:?}",
+        maths::bootstrap(&per_minute, maths::median, 2000, 0.95, 1)
+    );
+    println!();
+
+    let squares: Vec<i32> = (0..200).map(|i| (i * i) % 97).collect();
+    if let Ok(histogram) = Histogram::new(&squares, Binning::Sturges) {
+        println!("Histogram of i^2 % 97 for i in 0..200 (Sturges)");
+        print!("{histogram}");
     }
 }
@@ -0,0 +1,4 @@
+pub mod maths;
+pub mod persistent_vector;
+pub mod ref_cells;
+pub mod weak_cache;
@@ -0,0 +1,148 @@
+use super::error::check_not_empty;
+use super::{quantiles, Interpolation, Numeric, Rng, StatsError};
+
+// How uncertain a statistic of a sample is, by resampling: draw n values from the sample with
+// replacement, compute the statistic on that, and repeat. The spread of those statistics stands
+// in for the spread we'd see if we could collect fresh samples.
+// https://en.wikipedia.org/wiki/Bootstrapping_(statistics)
+
+// A statistic of the sample and the range it probably lies in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ConfidenceInterval {
+    // The statistic of the whole sample.
+    pub estimate: f64,
+    pub lower: f64,
+    pub upper: f64,
+}
+
+// A percentile interval for `statistic` of `v`: with confidence 0.95, the 2.5th and 97.5th
+// percentiles of the statistic over `resamples` resamples. `statistic` can be any of the maths
+// functions that turn a slice into a number, like median or mean, or a closure. A few thousand
+// resamples is usual.
+//
+// The same seed always gives the same interval. Fails if `v` is empty, if `resamples` is 0, if
+// `confidence` isn't strictly between 0 and 1, or with the first error `statistic` returns.
+pub fn bootstrap<T, F>(
+    v: &[T],
+    statistic: F,
+    resamples: usize,
+    confidence: f64,
+    seed: u64,
+) -> Result<ConfidenceInterval, StatsError>
+where
+    T: Numeric,
+    F: Fn(&[T]) -> Result<f64, StatsError>,
+{
+    if resamples == 0 {
+        return Err(StatsError::NoResamples);
+    }
+    if !(confidence > 0.0 && confidence < 1.0) {
+        return Err(StatsError::InvalidConfidence(confidence));
+    }
+    check_not_empty(v)?;
+    let estimate = statistic(v)?;
+
+    let mut rng = Rng::new(seed);
+    let mut resample = Vec::with_capacity(v.len());
+    let mut statistics = Vec::with_capacity(resamples);
+    for _ in 0..resamples {
+        resample.clear();
+        resample.extend((0..v.len()).map(|_| v[rng.below(v.len())]));
+        statistics.push(statistic(&resample)?);
+    }
+
+    let tail = (1.0 - confidence) / 2.0;
+    let bounds = quantiles(&statistics, &[tail, 1.0 - tail], Interpolation::Linear)?;
+    Ok(ConfidenceInterval {
+        estimate,
+        lower: bounds[0],
+        upper: bounds[1],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maths;
+
+    fn samples(len: usize) -> Vec<i32> {
+        let mut rng = Rng::new(1);
+        (0..len).map(|_| rng.below(1000) as i32).collect()
+    }
+
+    #[test]
+    fn same_seed_same_interval() {
+        let v = samples(200);
+
+        let first = bootstrap(&v, maths::median, 500, 0.95, 1);
+        assert_eq!(first, bootstrap(&v, maths::median, 500, 0.95, 1));
+        assert_ne!(first, bootstrap(&v, maths::median, 500, 0.95, 2));
+    }
+
+    #[test]
+    fn interval_surrounds_the_estimate() {
+        let v = samples(500);
+
+        for statistic in [maths::median, maths::mean] {
+            let interval = bootstrap(&v, statistic, 1000, 0.95, 3).unwrap();
+            assert!(interval.lower < interval.estimate && interval.estimate < interval.upper);
+        }
+    }
+
+    #[test]
+    fn mean_interval_matches_the_normal_approximation() {
+        // For the mean, the 95% interval should be close to 1.96 standard errors either side.
+        let v = samples(1000);
+        let standard_error = maths::sample_stddev(&v).unwrap() / (v.len() as f64).sqrt();
+
+        let interval = bootstrap(&v, maths::mean, 2000, 0.95, 4).unwrap();
+        let half_width = (interval.upper - interval.lower) / 2.0;
+        assert!(
+            (half_width / (1.96 * standard_error) - 1.0).abs() < 0.1,
+            "{half_width} vs {}",
+            1.96 * standard_error
+        );
+    }
+
+    #[test]
+    fn more_confidence_is_a_wider_interval() {
+        let v = samples(300);
+
+        let narrow = bootstrap(&v, maths::median, 1000, 0.5, 5).unwrap();
+        let wide = bootstrap(&v, maths::median, 1000, 0.99, 5).unwrap();
+        assert!(wide.lower < narrow.lower && narrow.upper < wide.upper);
+    }
+
+    #[test]
+    fn closures_work_as_statistics() {
+        let v = samples(100);
+        let p90 = |v: &[i32]| maths::quantile(v, 0.9, Interpolation::Linear);
+
+        let interval = bootstrap(&v, p90, 200, 0.9, 6).unwrap();
+        assert_eq!(Ok(interval.estimate), p90(&v));
+    }
+
+    #[test]
+    fn bad_input_is_an_error() {
+        assert_eq!(
+            bootstrap::<i32, _>(&[], maths::median, 10, 0.95, 0),
+            Err(StatsError::Empty)
+        );
+        assert_eq!(
+            bootstrap(&[1, 2], maths::median, 10, 1.0, 0),
+            Err(StatsError::InvalidConfidence(1.0))
+        );
+        assert_eq!(
+            bootstrap(&[1, 2], maths::median, 0, 0.95, 0),
+            Err(StatsError::NoResamples)
+        );
+        assert_eq!(
+            bootstrap(&[1.0, f64::NAN], maths::median, 10, 0.95, 0),
+            Err(StatsError::NonFinite)
+        );
+        assert_eq!(
+            bootstrap(&[1], maths::sample_variance, 10, 0.95, 0),
+            Err(StatsError::NotEnoughValues { needed: 2, got: 1 })
+        );
+    }
+}
@@ -0,0 +1,447 @@
+use super::error::{check_no_nan, check_not_empty};
+use super::mode::equal_runs;
+use super::nan::first_nan;
+use super::{NanPolicy, Numeric, StatsError};
+
+// Statistics relating two series of samples, where x[i] and y[i] were measured together. x and y
+// can be different Numeric types, but must be the same length (LengthMismatch otherwise). NaNs
+// are handled by the *_with variants' NanPolicy, as for the single-series functions. Skip drops
+// any pair with a NaN in it, and the plain functions use Error.
+
+// Mean, squared differences from the mean, and co-moment of x and y, built up one pair at a time
+// the same way RunningStats does for one series, so nothing is lost to cancellation.
+// https://en.wikipedia.org/wiki/Algorithms_for_calculating_variance#Covariance
+#[derive(Debug, Default)]
+struct CoMoments {
+    count: usize,
+    mean_x: f64,
+    mean_y: f64,
+    m2_x: f64,
+    m2_y: f64,
+    // Sum of (x - mean_x) * (y - mean_y).
+    c_xy: f64,
+}
+
+impl CoMoments {
+    fn push(&mut self, x: f64, y: f64) {
+        self.count += 1;
+        let n = self.count as f64;
+        let delta_x = x - self.mean_x;
+        let delta_y = y - self.mean_y;
+        self.mean_x += delta_x / n;
+        self.mean_y += delta_y / n;
+        self.m2_x += delta_x * (x - self.mean_x);
+        self.m2_y += delta_y * (y - self.mean_y);
+        self.c_xy += delta_x * (y - self.mean_y);
+    }
+
+    // Pearson's r. ZeroVariance if either series is constant, since r is 0 / 0 then.
+    fn correlation(&self) -> Result<f64, StatsError> {
+        if self.m2_x == 0.0 || self.m2_y == 0.0 {
+            return Err(StatsError::ZeroVariance);
+        }
+        // One square root rounds less, so perfect correlations come out as exactly 1, but the
+        // product overflows for values around 1e100 (or underflows for tiny ones). Then square
+        // root each on its own.
+        let product = self.m2_x * self.m2_y;
+        let denominator = if product.is_normal() {
+            product.sqrt()
+        } else {
+            self.m2_x.sqrt() * self.m2_y.sqrt()
+        };
+        // Rounding can push a perfect correlation a hair past 1.
+        Ok((self.c_xy / denominator).clamp(-1.0, 1.0))
+    }
+}
+
+fn check_same_length<X, Y>(x: &[X], y: &[Y]) -> Result<(), StatsError> {
+    if x.len() != y.len() {
+        return Err(StatsError::LengthMismatch {
+            left: x.len(),
+            right: y.len(),
+        });
+    }
+    Ok(())
+}
+
+// x and y must already have been through NanPolicy::filter_pairs.
+fn co_moments<X: Numeric, Y: Numeric>(x: &[X], y: &[Y]) -> Result<CoMoments, StatsError> {
+    check_not_empty(x)?;
+
+    let mut moments = CoMoments::default();
+    for (&x, &y) in x.iter().zip(y) {
+        moments.push(x.to_f64(), y.to_f64());
+    }
+    Ok(moments)
+}
+
+// Checks a result computed from both series, like check_result does for one. A non-finite result
+// is the input's fault if either series had a NaN or an infinity in it, and an overflow only if
+// both were all finite.
+fn check_pair_result<X: Numeric, Y: Numeric>(
+    result: f64,
+    x: &[X],
+    y: &[Y],
+    nan: NanPolicy,
+) -> Result<f64, StatsError> {
+    if result.is_finite() {
+        Ok(result)
+    } else if x.iter().all(|a| a.is_finite()) && y.iter().all(|b| b.is_finite()) {
+        Err(StatsError::Overflow)
+    } else if nan == NanPolicy::Propagate {
+        Ok(result)
+    } else {
+        Err(StatsError::NonFinite)
+    }
+}
+
+// Divides by n. Use this when the pairs are the whole population.
+pub fn population_covariance<X: Numeric, Y: Numeric>(x: &[X], y: &[Y]) -> Result<f64, StatsError> {
+    population_covariance_with(x, y, NanPolicy::Error)
+}
+
+pub fn population_covariance_with<X: Numeric, Y: Numeric>(
+    x: &[X],
+    y: &[Y],
+    nan: NanPolicy,
+) -> Result<f64, StatsError> {
+    check_same_length(x, y)?;
+    let (x, y) = nan.filter_pairs(x, y)?;
+    let moments = co_moments(&x, &y)?;
+    check_pair_result(moments.c_xy / moments.count as f64, &x, &y, nan)
+}
+
+// Divides by n - 1. Needs at least two pairs (after skipping NaNs, for Skip).
+pub fn sample_covariance<X: Numeric, Y: Numeric>(x: &[X], y: &[Y]) -> Result<f64, StatsError> {
+    sample_covariance_with(x, y, NanPolicy::Error)
+}
+
+pub fn sample_covariance_with<X: Numeric, Y: Numeric>(
+    x: &[X],
+    y: &[Y],
+    nan: NanPolicy,
+) -> Result<f64, StatsError> {
+    check_same_length(x, y)?;
+    let (x, y) = nan.filter_pairs(x, y)?;
+    let moments = co_moments(&x, &y)?;
+    if moments.count < 2 {
+        return Err(StatsError::NotEnoughValues {
+            needed: 2,
+            got: moments.count,
+        });
+    }
+    check_pair_result(moments.c_xy / (moments.count - 1) as f64, &x, &y, nan)
+}
+
+// Pearson's r, in [-1, 1]: how close the pairs are to lying on a straight line.
+// https://en.wikipedia.org/wiki/Pearson_correlation_coefficient
+pub fn pearson_correlation<X: Numeric, Y: Numeric>(x: &[X], y: &[Y]) -> Result<f64, StatsError> {
+    pearson_correlation_with(x, y, NanPolicy::Error)
+}
+
+pub fn pearson_correlation_with<X: Numeric, Y: Numeric>(
+    x: &[X],
+    y: &[Y],
+    nan: NanPolicy,
+) -> Result<f64, StatsError> {
+    check_same_length(x, y)?;
+    let (x, y) = nan.filter_pairs(x, y)?;
+    let r = co_moments(&x, &y)?.correlation()?;
+    check_pair_result(r, &x, &y, nan)
+}
+
+// Spearman's rho, in [-1, 1]: Pearson's r of the ranks, so it measures how monotonic the
+// relationship is, whatever its shape, and one huge outlier can't dominate it.
+// https://en.wikipedia.org/wiki/Spearman%27s_rank_correlation_coefficient
+pub fn spearman_correlation<X: Numeric, Y: Numeric>(x: &[X], y: &[Y]) -> Result<f64, StatsError> {
+    spearman_correlation_with(x, y, NanPolicy::Error)
+}
+
+// NaNs have no rank, so under Propagate any NaN makes rho that NaN.
+pub fn spearman_correlation_with<X: Numeric, Y: Numeric>(
+    x: &[X],
+    y: &[Y],
+    nan: NanPolicy,
+) -> Result<f64, StatsError> {
+    check_same_length(x, y)?;
+    let (x, y) = nan.filter_pairs(x, y)?;
+    check_not_empty(&x)?;
+    if let Some(nan) = first_nan(&x) {
+        return Ok(nan.to_f64());
+    }
+    if let Some(nan) = first_nan(&y) {
+        return Ok(nan.to_f64());
+    }
+    pearson_correlation(&ranks(&x)?, &ranks(&y)?)
+}
+
+// The 1-based rank of each value in sorted order, in the original order. Tied values all get the
+// average of the ranks they span, e.g. [10, 20, 20, 30] ranks as [1.0, 2.5, 2.5, 4.0].
+pub fn ranks<T: Numeric>(v: &[T]) -> Result<Vec<f64>, StatsError> {
+    check_not_empty(v)?;
+    check_no_nan(v)?;
+
+    let mut order: Vec<usize> = (0..v.len()).collect();
+    order.sort_by(|&a, &b| v[a].total_cmp(&v[b]));
+
+    let mut ranks = vec![0.0; v.len()];
+    for run in equal_runs(&order, |&a, &b| v[a].total_cmp(&v[b]).is_eq()) {
+        // Ranks run.start + 1 through run.end, averaged.
+        let rank = (run.start + 1 + run.end) as f64 / 2.0;
+        for &i in &order[run] {
+            ranks[i] = rank;
+        }
+    }
+
+    Ok(ranks)
+}
+
+// The least-squares line y = slope * x + intercept.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LinearRegression {
+    pub slope: f64,
+    pub intercept: f64,
+    // The fraction of y's variance the line explains: 1 is a perfect fit, 0 is no better than
+    // always guessing the mean of y. 1 when y is constant, since the flat line fits it exactly.
+    pub r_squared: f64,
+}
+
+impl LinearRegression {
+    pub fn predict(&self, x: f64) -> f64 {
+        self.slope * x + self.intercept
+    }
+}
+
+// ZeroVariance if every x is the same, since any line through them would fit as well as any other.
+// https://en.wikipedia.org/wiki/Simple_linear_regression
+pub fn linear_regression<X: Numeric, Y: Numeric>(
+    x: &[X],
+    y: &[Y],
+) -> Result<LinearRegression, StatsError> {
+    linear_regression_with(x, y, NanPolicy::Error)
+}
+
+pub fn linear_regression_with<X: Numeric, Y: Numeric>(
+    x: &[X],
+    y: &[Y],
+    nan: NanPolicy,
+) -> Result<LinearRegression, StatsError> {
+    check_same_length(x, y)?;
+    let (x, y) = nan.filter_pairs(x, y)?;
+    let moments = co_moments(&x, &y)?;
+    if moments.m2_x == 0.0 {
+        return Err(StatsError::ZeroVariance);
+    }
+
+    let slope = check_pair_result(moments.c_xy / moments.m2_x, &x, &y, nan)?;
+    let intercept = check_pair_result(moments.mean_y - slope * moments.mean_x, &x, &y, nan)?;
+    let r_squared = match moments.correlation() {
+        Ok(r) => r * r,
+        Err(_) => 1.0,
+    };
+
+    Ok(LinearRegression {
+        slope,
+        intercept,
+        r_squared,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-9,
+            "expected {expected}, got {actual}"
+        );
+    }
+
+    // Anscombe's first quartet: r = 0.816, y = 0.5x + 3.
+    // https://en.wikipedia.org/wiki/Anscombe%27s_quartet
+    const X: [i32; 11] = [10, 8, 13, 9, 11, 14, 6, 4, 12, 7, 5];
+    const Y: [f64; 11] = [
+        8.04, 6.95, 7.58, 8.81, 8.33, 9.96, 7.24, 4.26, 10.84, 4.82, 5.68,
+    ];
+
+    #[test]
+    fn covariance_works() {
+        let x = [1, 2, 3, 4];
+        let y = [2.0, 4.0, 6.0, 8.0];
+
+        assert_eq!(population_covariance(&x, &y), Ok(2.5));
+        assert_close(sample_covariance(&x, &y).unwrap(), 10.0 / 3.0);
+        // The covariance of a series with itself is its variance.
+        assert_close(
+            sample_covariance(&X, &X).unwrap(),
+            crate::maths::sample_variance(&X).unwrap(),
+        );
+    }
+
+    #[test]
+    fn pearson_and_regression_match_anscombe() {
+        let fit = linear_regression(&X, &Y).unwrap();
+
+        assert!((pearson_correlation(&X, &Y).unwrap() - 0.816).abs() < 1e-3);
+        assert!((fit.slope - 0.500).abs() < 1e-3);
+        assert!((fit.intercept - 3.00).abs() < 1e-2);
+        assert!((fit.r_squared - 0.67).abs() < 1e-2);
+        assert!((fit.predict(10.0) - 8.0).abs() < 1e-2);
+    }
+
+    #[test]
+    fn perfect_lines_correlate_perfectly() {
+        let x = [1.0, 2.0, 3.0, 4.0];
+        let up = [3.0, 5.0, 7.0, 9.0];
+        let down = [0, -10, -20, -30];
+
+        assert_eq!(pearson_correlation(&x, &up), Ok(1.0));
+        assert_eq!(pearson_correlation(&x, &down), Ok(-1.0));
+        assert_eq!(
+            linear_regression(&x, &up),
+            Ok(LinearRegression {
+                slope: 2.0,
+                intercept: 1.0,
+                r_squared: 1.0
+            })
+        );
+    }
+
+    #[test]
+    fn huge_values_still_correlate() {
+        let x = [1e100, 2e100, 3e100];
+        let y = [-1e100, -2e100, -3e100];
+
+        assert_eq!(pearson_correlation(&x, &x), Ok(1.0));
+        assert_eq!(pearson_correlation(&x, &y), Ok(-1.0));
+        assert_eq!(linear_regression(&x, &x).unwrap().r_squared, 1.0);
+    }
+
+    #[test]
+    fn spearman_only_cares_about_order() {
+        let x = [1, 2, 3, 4, 5];
+        let y = [1.0, 8.0, 27.0, 64.0, 1e9];
+
+        assert_eq!(spearman_correlation(&x, &y), Ok(1.0));
+        assert!(pearson_correlation(&x, &y).unwrap() < 0.8);
+        assert_eq!(spearman_correlation(&x, &[5, 4, 3, 2, 1]), Ok(-1.0));
+    }
+
+    #[test]
+    fn ranks_average_ties() {
+        assert_eq!(ranks(&[10, 20, 20, 30]), Ok(vec![1.0, 2.5, 2.5, 4.0]));
+        assert_eq!(ranks(&[3.0, 1.0, 3.0, 3.0]), Ok(vec![3.0, 1.0, 3.0, 3.0]));
+    }
+
+    #[test]
+    fn infinity_in_either_series_is_non_finite() {
+        let finite = [1, 2, 3];
+        let infinite = [1.0, f64::INFINITY, 3.0];
+
+        assert_eq!(
+            pearson_correlation(&infinite, &finite),
+            Err(StatsError::NonFinite)
+        );
+        assert_eq!(
+            pearson_correlation(&finite, &infinite),
+            Err(StatsError::NonFinite)
+        );
+        assert_eq!(
+            population_covariance(&infinite, &finite),
+            Err(StatsError::NonFinite)
+        );
+        assert_eq!(
+            population_covariance(&finite, &infinite),
+            Err(StatsError::NonFinite)
+        );
+        assert_eq!(
+            linear_regression(&finite, &infinite),
+            Err(StatsError::NonFinite)
+        );
+
+        // Finite input whose covariance doesn't fit in an f64 is an overflow.
+        let huge = [-1e200, 1e200];
+        assert_eq!(
+            population_covariance(&huge, &huge),
+            Err(StatsError::Overflow)
+        );
+    }
+
+    #[test]
+    fn nan_pairs_follow_the_nan_policy() {
+        let x = [1.0, 2.0, f64::NAN, 3.0, 4.0];
+        let y = [2.0, 4.0, 5.0, 6.0, f64::NAN];
+
+        assert_eq!(pearson_correlation(&x, &y), Err(StatsError::NonFinite));
+        // Skip drops the third and fifth pairs, leaving a perfect line.
+        assert_eq!(pearson_correlation_with(&x, &y, NanPolicy::Skip), Ok(1.0));
+        assert_eq!(spearman_correlation_with(&x, &y, NanPolicy::Skip), Ok(1.0));
+        assert_eq!(
+            population_covariance_with(&x, &y, NanPolicy::Skip),
+            population_covariance(&[1, 2, 3], &[2, 4, 6])
+        );
+        assert_eq!(
+            sample_covariance_with(&x, &y, NanPolicy::Skip),
+            sample_covariance(&[1, 2, 3], &[2, 4, 6])
+        );
+        assert_eq!(
+            linear_regression_with(&x, &y, NanPolicy::Skip).map(|fit| fit.slope),
+            Ok(2.0)
+        );
+
+        for r in [
+            pearson_correlation_with(&x, &y, NanPolicy::Propagate),
+            spearman_correlation_with(&x, &y, NanPolicy::Propagate),
+            sample_covariance_with(&x, &y, NanPolicy::Propagate),
+            linear_regression_with(&x, &y, NanPolicy::Propagate).map(|fit| fit.slope),
+        ] {
+            assert!(r.unwrap().is_nan());
+        }
+        assert_eq!(
+            pearson_correlation_with(&[f64::NAN], &[1.0], NanPolicy::Skip),
+            Err(StatsError::Empty)
+        );
+    }
+
+    #[test]
+    fn bad_input_is_an_error() {
+        let mismatch = Err(StatsError::LengthMismatch { left: 3, right: 2 });
+
+        assert_eq!(population_covariance(&[1, 2, 3], &[1, 2]), mismatch);
+        assert_eq!(spearman_correlation(&[1, 2, 3], &[1, 2]), mismatch);
+        assert_eq!(
+            linear_regression(&[1, 2, 3], &[1, 2]).map(|fit| fit.slope),
+            mismatch
+        );
+        assert_eq!(
+            pearson_correlation::<i32, i32>(&[], &[]),
+            Err(StatsError::Empty)
+        );
+        assert_eq!(
+            sample_covariance(&[1], &[1]),
+            Err(StatsError::NotEnoughValues { needed: 2, got: 1 })
+        );
+        assert_eq!(
+            pearson_correlation(&[1, 1, 1], &[1, 2, 3]),
+            Err(StatsError::ZeroVariance)
+        );
+        assert_eq!(
+            linear_regression(&[2, 2], &[1, 5]),
+            Err(StatsError::ZeroVariance)
+        );
+        assert_eq!(
+            pearson_correlation(&[1.0, f64::NAN], &[1, 2]),
+            Err(StatsError::NonFinite)
+        );
+    }
+
+    #[test]
+    fn constant_y_is_a_perfect_flat_fit() {
+        let fit = linear_regression(&[1, 2, 3], &[4, 4, 4]).unwrap();
+
+        assert_eq!(fit.slope, 0.0);
+        assert_eq!(fit.intercept, 4.0);
+        assert_eq!(fit.r_squared, 1.0);
+    }
+}
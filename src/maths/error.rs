@@ -0,0 +1,102 @@
+use std::error::Error;
+use std::fmt;
+
+use super::{NanPolicy, Numeric};
+
+// Why a statistic couldn't be computed. Lets callers tell "there was no data" (Empty) apart from
+// "the data or arguments were bad" (everything else).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StatsError {
+    // The input had no values.
+    Empty,
+    // The statistic needs more values than it got, e.g. sample variance of a single value.
+    NotEnoughValues { needed: usize, got: usize },
+    // The input had a NaN, or an infinity turned the result into one.
+    NonFinite,
+    // Every input was finite, but the result overflowed to infinity.
+    Overflow,
+    // A quantile outside [0, 1].
+    InvalidQuantile(f64),
+    // Two series that should be paired up have different lengths.
+    LengthMismatch { left: usize, right: usize },
+    // The statistic divides by a variance that's 0, e.g. the correlation of a constant series.
+    ZeroVariance,
+    // A weight that's negative or doesn't fit in a usize count.
+    InvalidWeight,
+    // A histogram bin width that isn't positive and finite, bin edges that aren't at least two
+    // strictly increasing finite values, or a binning that would need more than a million bins.
+    InvalidBinning,
+    // An outlier threshold that isn't positive and finite.
+    InvalidThreshold(f64),
+    // A fraction to trim off each end that's outside [0, 0.5).
+    InvalidProportion(f64),
+    // A confidence level that isn't strictly between 0 and 1.
+    InvalidConfidence(f64),
+    // A bootstrap asked for zero resamples.
+    NoResamples,
+    // A count-min sketch error bound (epsilon or delta) that isn't strictly between 0 and 1.
+    InvalidErrorBound(f64),
+}
+
+impl fmt::Display for StatsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StatsError::Empty => write!(f, "no values"),
+            StatsError::NotEnoughValues { needed, got } => {
+                write!(f, "needs at least {needed} values, got {got}")
+            }
+            StatsError::NonFinite => write!(f, "input contains NaN or infinite values"),
+            StatsError::Overflow => write!(f, "result overflowed"),
+            StatsError::InvalidQuantile(q) => write!(f, "quantile {q} is outside [0, 1]"),
+            StatsError::LengthMismatch { left, right } => {
+                write!(f, "series have different lengths ({left} and {right})")
+            }
+            StatsError::ZeroVariance => write!(f, "a series has zero variance"),
+            StatsError::InvalidWeight => write!(f, "weights must be non-negative counts"),
+            StatsError::InvalidBinning => write!(f, "invalid histogram bins"),
+            StatsError::InvalidThreshold(t) => write!(f, "threshold {t} must be positive"),
+            StatsError::InvalidProportion(p) => write!(f, "proportion {p} is outside [0, 0.5)"),
+            StatsError::InvalidConfidence(c) => write!(f, "confidence {c} is outside (0, 1)"),
+            StatsError::NoResamples => write!(f, "needs at least one resample"),
+            StatsError::InvalidErrorBound(e) => write!(f, "error bound {e} is outside (0, 1)"),
+        }
+    }
+}
+
+impl Error for StatsError {}
+
+pub(crate) fn check_not_empty<T>(v: &[T]) -> Result<(), StatsError> {
+    if v.is_empty() {
+        Err(StatsError::Empty)
+    } else {
+        Ok(())
+    }
+}
+
+// Order statistics (median, quantiles, min, max) work fine with infinities, but NaN has no place
+// in the order, so any NaN is an error.
+pub(crate) fn check_no_nan<T: Numeric>(v: &[T]) -> Result<(), StatsError> {
+    if v.iter().any(|x| x.is_nan()) {
+        Err(StatsError::NonFinite)
+    } else {
+        Ok(())
+    }
+}
+
+// Checks a computed result. A non-finite result is the input's fault if the input had a NaN or
+// an infinity in it (and passed through as is under Propagate), and an overflow otherwise.
+pub(crate) fn check_result<T: Numeric>(
+    result: f64,
+    v: &[T],
+    nan: NanPolicy,
+) -> Result<f64, StatsError> {
+    if result.is_finite() {
+        Ok(result)
+    } else if v.iter().all(|x| x.is_finite()) {
+        Err(StatsError::Overflow)
+    } else if nan == NanPolicy::Propagate {
+        Ok(result)
+    } else {
+        Err(StatsError::NonFinite)
+    }
+}
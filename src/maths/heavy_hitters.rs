@@ -0,0 +1,420 @@
+use std::cmp::Reverse;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+use super::{Modes, StatsError};
+
+// Approximate modes of a stream, for when count_values' one-entry-per-distinct-value HashMap is
+// too big. Both structures only keep a fixed number of counters, so counts come back as bounds
+// instead of exact values. mode() returns the same Modes as mode_n, so callers can switch between
+// exact and approximate.
+
+// The true count of a value is somewhere in lower..=upper.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CountBounds {
+    pub lower: usize,
+    pub upper: usize,
+}
+
+impl CountBounds {
+    pub fn contains(&self, count: usize) -> bool {
+        (self.lower..=self.upper).contains(&count)
+    }
+}
+
+// Misra-Gries "frequent items": k counters. A new value takes a free counter if there is one;
+// otherwise every counter is decremented instead. Each decrement round cancels k + 1 occurrences
+// (the k tracked plus the new one), so it can happen at most n / (k + 1) times, and that's also
+// the most any count can be under by. Anything that makes up more than 1 / (k + 1) of the stream
+// is guaranteed to still be tracked.
+// https://en.wikipedia.org/wiki/Misra%E2%80%93Gries_summary
+#[derive(Debug, Clone)]
+pub struct MisraGries<T> {
+    k: usize,
+    counters: HashMap<T, usize>,
+    count: usize,
+    // How many decrement rounds have happened, i.e. the most any counter can be under by.
+    decrements: usize,
+}
+
+impl<T: Hash + Eq + Clone> MisraGries<T> {
+    // Panics if `k` is 0.
+    pub fn new(k: usize) -> MisraGries<T> {
+        assert!(k != 0, "k must be non-zero");
+
+        MisraGries {
+            k,
+            counters: HashMap::with_capacity(k),
+            count: 0,
+            decrements: 0,
+        }
+    }
+
+    pub fn add(&mut self, x: T) {
+        self.count += 1;
+        if let Some(counter) = self.counters.get_mut(&x) {
+            *counter += 1;
+        } else if self.counters.len() < self.k {
+            self.counters.insert(x, 1);
+        } else {
+            self.decrements += 1;
+            self.counters.retain(|_, counter| {
+                *counter -= 1;
+                *counter > 0
+            });
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    pub fn estimate(&self, x: &T) -> CountBounds {
+        let lower = self.counters.get(x).copied().unwrap_or(0);
+        CountBounds {
+            lower,
+            upper: lower + self.decrements,
+        }
+    }
+
+    // Every tracked value with its bounds, most frequent first.
+    pub fn heavy_hitters(&self) -> Vec<(T, CountBounds)> {
+        let mut hitters: Vec<(T, CountBounds)> = self
+            .counters
+            .keys()
+            .map(|x| (x.clone(), self.estimate(x)))
+            .collect();
+        hitters.sort_by_key(|&(_, bounds)| Reverse(bounds.lower));
+        hitters
+    }
+
+    // The tracked values with the highest count. If the real mode makes up more than
+    // 1 / (k + 1) of the stream, it's in here. The count is the lower bound.
+    pub fn mode(&self) -> Result<Modes<T>, StatsError>
+    where
+        T: Ord,
+    {
+        Modes::from_counts(self.counters.iter().map(|(x, &count)| (x, count)))
+    }
+}
+
+// Count-Min sketch: `depth` rows of `width` counters, each row with its own hash. Adding a
+// value bumps one counter per row, and its estimate is the smallest of those counters. Other
+// values colliding with it can only push counters up, so the estimate never undercounts, and
+// with width = e / epsilon and depth = ln(1 / delta) it overcounts by at most epsilon * n with
+// probability 1 - delta.
+// https://en.wikipedia.org/wiki/Count%E2%80%93min_sketch
+//
+// The sketch can't list the values it has seen, so it also keeps the `k` values with the highest
+// estimates so far as mode candidates. Adding a value is O(depth) unless it pushes out a
+// candidate, which takes an O(k) scan for the one to replace. In a long tail most values never
+// get past the smallest candidate, so that scan is rare.
+#[derive(Debug, Clone)]
+pub struct CountMinSketch<T> {
+    width: usize,
+    depth: usize,
+    counters: Vec<usize>,
+    count: usize,
+    k: usize,
+    candidates: HashMap<T, usize>,
+    // No candidate's count is below this once there are k of them. It can lag behind, since
+    // counts only go up.
+    floor: usize,
+}
+
+impl<T: Hash + Eq + Clone> CountMinSketch<T> {
+    // Panics if `width`, `depth` or `k` is 0.
+    pub fn new(width: usize, depth: usize, k: usize) -> CountMinSketch<T> {
+        assert!(width != 0 && depth != 0, "width and depth must be non-zero");
+        assert!(k != 0, "k must be non-zero");
+
+        CountMinSketch {
+            width,
+            depth,
+            counters: vec![0; width * depth],
+            count: 0,
+            k,
+            candidates: HashMap::with_capacity(k),
+            floor: 0,
+        }
+    }
+
+    // Sized so estimates are within epsilon * n of the true count with probability 1 - delta.
+    // InvalidErrorBound unless epsilon and delta are strictly between 0 and 1, since 0 would need
+    // an infinitely big table. Panics if `k` is 0, like new.
+    pub fn with_error(epsilon: f64, delta: f64, k: usize) -> Result<CountMinSketch<T>, StatsError> {
+        for bound in [epsilon, delta] {
+            if !(bound > 0.0 && bound < 1.0) {
+                return Err(StatsError::InvalidErrorBound(bound));
+            }
+        }
+
+        let width = (std::f64::consts::E / epsilon).ceil() as usize;
+        let depth = (1.0 / delta).ln().ceil() as usize;
+        Ok(CountMinSketch::new(width, depth.max(1), k))
+    }
+
+    // DefaultHasher::new() always uses the same keys, so mixing the row number in gives each row
+    // its own deterministic hash function.
+    fn index(&self, row: usize, x: &T) -> usize {
+        let mut hasher = DefaultHasher::new();
+        row.hash(&mut hasher);
+        x.hash(&mut hasher);
+        row * self.width + (hasher.finish() % self.width as u64) as usize
+    }
+
+    fn raw_estimate(&self, x: &T) -> usize {
+        (0..self.depth)
+            .map(|row| self.counters[self.index(row, x)])
+            .min()
+            .unwrap_or(0)
+    }
+
+    pub fn add(&mut self, x: T) {
+        self.count += 1;
+        for row in 0..self.depth {
+            let i = self.index(row, &x);
+            self.counters[i] += 1;
+        }
+
+        let estimate = self.raw_estimate(&x);
+        if let Some(count) = self.candidates.get_mut(&x) {
+            *count = estimate;
+            return;
+        }
+        if self.candidates.len() < self.k {
+            self.candidates.insert(x, estimate);
+            return;
+        }
+        if estimate <= self.floor {
+            return;
+        }
+
+        // The floor may be out of date, so look for the actual smallest candidate.
+        let smallest = self
+            .candidates
+            .iter()
+            .min_by_key(|&(_, &count)| count)
+            .map(|(item, &count)| (item.clone(), count));
+        if let Some((smallest, count)) = smallest {
+            if count >= estimate {
+                self.floor = count;
+                return;
+            }
+            self.candidates.remove(&smallest);
+        }
+        self.candidates.insert(x, estimate);
+        self.floor = self.candidates.values().copied().min().unwrap_or(0);
+    }
+
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    // How much an estimate can overcount by (with probability 1 - e^-depth).
+    pub fn error_bound(&self) -> usize {
+        (std::f64::consts::E / self.width as f64 * self.count as f64).ceil() as usize
+    }
+
+    pub fn estimate(&self, x: &T) -> CountBounds {
+        let upper = self.raw_estimate(x);
+        CountBounds {
+            lower: upper.saturating_sub(self.error_bound()),
+            upper,
+        }
+    }
+
+    // The mode candidates with their bounds, most frequent first.
+    pub fn heavy_hitters(&self) -> Vec<(T, CountBounds)> {
+        let mut hitters: Vec<(T, CountBounds)> = self
+            .candidates
+            .keys()
+            .map(|x| (x.clone(), self.estimate(x)))
+            .collect();
+        hitters.sort_by_key(|&(_, bounds)| Reverse(bounds.upper));
+        hitters
+    }
+
+    // The candidates with the highest estimate. The count is the upper bound.
+    pub fn mode(&self) -> Result<Modes<T>, StatsError>
+    where
+        T: Ord,
+    {
+        Modes::from_counts(self.candidates.keys().map(|x| (x, self.raw_estimate(x))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maths;
+    use std::collections::HashSet;
+
+    // A skewed stream: value i shows up roughly 1000 / (i + 1) times, shuffled, plus a long tail
+    // of values that only show up once.
+    fn skewed_stream() -> Vec<u32> {
+        let mut v: Vec<u32> = (0..50u32)
+            .flat_map(|i| std::iter::repeat_n(i, 1000 / (i as usize + 1)))
+            .chain(1000..6000)
+            .collect();
+        let mut rng = maths::Rng::new(12345);
+        for i in (1..v.len()).rev() {
+            v.swap(i, rng.below(i + 1));
+        }
+        v
+    }
+
+    fn exact_counts(v: &[u32]) -> HashMap<u32, usize> {
+        let mut counts = HashMap::new();
+        for &x in v {
+            *counts.entry(x).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    #[test]
+    fn misra_gries_finds_the_mode() {
+        let v = skewed_stream();
+        let mut mg = MisraGries::new(20);
+        for &x in &v {
+            mg.add(x);
+        }
+
+        let (exact, approximate) = (maths::mode_n(&v).unwrap(), mg.mode().unwrap());
+        assert_eq!(approximate.values, exact.values);
+        assert!(mg.estimate(&0).contains(exact.count));
+        assert_eq!(approximate.count, mg.estimate(&0).lower);
+        assert_eq!(mg.len(), v.len());
+    }
+
+    #[test]
+    fn misra_gries_bounds_contain_true_counts() {
+        let v = skewed_stream();
+        let k = 20;
+        let mut mg = MisraGries::new(k);
+        for &x in &v {
+            mg.add(x);
+        }
+
+        for (x, count) in exact_counts(&v) {
+            let bounds = mg.estimate(&x);
+            assert!(bounds.contains(count), "{x}: {count} not in {bounds:?}");
+            assert!(bounds.upper - bounds.lower <= v.len() / (k + 1));
+        }
+    }
+
+    #[test]
+    fn misra_gries_tracks_everything_above_threshold() {
+        let v = skewed_stream();
+        let k = 20;
+        let mut mg = MisraGries::new(k);
+        for &x in &v {
+            mg.add(x);
+        }
+
+        let tracked: HashSet<u32> = mg.heavy_hitters().into_iter().map(|(x, _)| x).collect();
+        for (x, count) in exact_counts(&v) {
+            if count > v.len() / (k + 1) {
+                assert!(tracked.contains(&x), "{x} appears {count} times");
+            }
+        }
+    }
+
+    #[test]
+    fn count_min_finds_the_mode() {
+        let v = skewed_stream();
+        let mut cms = CountMinSketch::with_error(0.001, 0.01, 10).unwrap();
+        for &x in &v {
+            cms.add(x);
+        }
+
+        let (exact, approximate) = (maths::mode_n(&v).unwrap(), cms.mode().unwrap());
+        assert_eq!(approximate.values, exact.values);
+        assert!(approximate.count >= exact.count);
+        assert_eq!(cms.heavy_hitters()[0].0, 0);
+    }
+
+    #[test]
+    fn count_min_never_undercounts() {
+        let v = skewed_stream();
+        let mut cms = CountMinSketch::new(200, 4, 10);
+        for &x in &v {
+            cms.add(x);
+        }
+
+        let counts = exact_counts(&v);
+        for (x, &count) in &counts {
+            assert!(cms.estimate(x).upper >= count);
+        }
+        // Most estimates are within the bound; a few may be unlucky.
+        let within = counts
+            .iter()
+            .filter(|&(x, &count)| cms.estimate(x).contains(count))
+            .count();
+        assert!(within as f64 >= 0.95 * counts.len() as f64);
+    }
+
+    #[test]
+    fn empty_structures_have_no_mode() {
+        let mg: MisraGries<i32> = MisraGries::new(5);
+        let cms: CountMinSketch<i32> = CountMinSketch::new(10, 2, 5);
+
+        assert_eq!(mg.mode(), Err(StatsError::Empty));
+        assert_eq!(cms.mode(), Err(StatsError::Empty));
+        assert_eq!(mg.mode(), maths::mode_n::<i32>(&[]));
+    }
+
+    #[test]
+    fn ties_are_all_reported() {
+        let v = [1, 2, 1, 2, 3];
+        let mut mg = MisraGries::new(5);
+        let mut cms = CountMinSketch::new(100, 3, 5);
+        for x in v {
+            mg.add(x);
+            cms.add(x);
+        }
+
+        assert_eq!(mg.mode().unwrap().values, [1, 2]);
+        assert_eq!(cms.mode().unwrap().values, [1, 2]);
+    }
+
+    #[test]
+    fn count_min_keeps_at_most_k_candidates() {
+        let v = skewed_stream();
+        let mut cms = CountMinSketch::new(2000, 4, 10);
+        for &x in &v {
+            cms.add(x);
+        }
+
+        let hitters = cms.heavy_hitters();
+        assert_eq!(hitters.len(), 10);
+        let expected: HashSet<u32> = (0..10).collect();
+        let found: HashSet<u32> = hitters.into_iter().map(|(x, _)| x).collect();
+        assert_eq!(found, expected);
+    }
+
+    #[test]
+    fn count_min_rejects_bad_error_bounds() {
+        for (epsilon, delta, bad) in [
+            (0.0, 0.01, 0.0),
+            (0.01, 0.0, 0.0),
+            (1.0, 0.01, 1.0),
+            (0.01, -0.5, -0.5),
+        ] {
+            assert_eq!(
+                CountMinSketch::<u32>::with_error(epsilon, delta, 10).map(|_| ()),
+                Err(StatsError::InvalidErrorBound(bad))
+            );
+        }
+        assert!(CountMinSketch::<u32>::with_error(f64::NAN, 0.01, 10).is_err());
+    }
+}
@@ -0,0 +1,489 @@
+use std::fmt;
+use std::hash::Hash;
+
+use super::error::check_not_empty;
+use super::{count_values, quantiles_with, Interpolation, NanPolicy, Numeric, StatsError};
+
+// Widest bar drawn by the Display impls, in characters.
+const BAR_WIDTH: usize = 50;
+
+// Draws one `label | ##### count` line per row, with bars scaled so the biggest count gets
+// BAR_WIDTH characters.
+fn bar_chart(f: &mut fmt::Formatter<'_>, rows: &[(String, usize)]) -> fmt::Result {
+    let label_width = rows.iter().map(|(label, _)| label.len()).max().unwrap_or(0);
+    let max_count = rows
+        .iter()
+        .map(|&(_, count)| count)
+        .max()
+        .unwrap_or(0)
+        .max(1);
+
+    for (label, count) in rows {
+        let bar = "#".repeat(count * BAR_WIDTH / max_count);
+        writeln!(f, "{label:>label_width$} | {bar} {count}")?;
+    }
+
+    Ok(())
+}
+
+// count_values, sorted by value, with cumulative counts. For discrete data where every distinct
+// value gets its own row.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrequencyTable<T> {
+    entries: Vec<(T, usize)>,
+}
+
+impl<T: Hash + Ord + Clone> FrequencyTable<T> {
+    pub fn new(v: &[T]) -> FrequencyTable<T> {
+        let mut entries: Vec<(T, usize)> = count_values(v)
+            .into_iter()
+            .map(|(value, count)| (value.clone(), count))
+            .collect();
+        entries.sort();
+
+        FrequencyTable { entries }
+    }
+
+    // Each distinct value and how many times it shows up, smallest value first.
+    pub fn entries(&self) -> &[(T, usize)] {
+        &self.entries
+    }
+
+    pub fn count(&self, value: &T) -> usize {
+        match self.entries.binary_search_by(|(x, _)| x.cmp(value)) {
+            Ok(i) => self.entries[i].1,
+            Err(_) => 0,
+        }
+    }
+
+    // Running totals in the same order as entries(): how many values are <= each value.
+    pub fn cumulative_counts(&self) -> Vec<(T, usize)> {
+        let mut total = 0;
+        self.entries
+            .iter()
+            .map(|(value, count)| {
+                total += count;
+                (value.clone(), total)
+            })
+            .collect()
+    }
+
+    pub fn total(&self) -> usize {
+        self.entries.iter().map(|&(_, count)| count).sum()
+    }
+}
+
+impl<T: fmt::Debug> fmt::Display for FrequencyTable<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rows: Vec<(String, usize)> = self
+            .entries
+            .iter()
+            .map(|(value, count)| (format!("{value:?}"), *count))
+            .collect();
+        bar_chart(f, &rows)
+    }
+}
+
+// How Histogram splits the data's range into bins.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Binning {
+    // Bins of this width, starting at the smallest value. At most a million of them.
+    FixedWidth(f64),
+    // Bins between consecutive edges, which must be strictly increasing. Values outside the
+    // first and last edge aren't counted (see Histogram::out_of_range).
+    Edges(Vec<f64>),
+    // ceil(log2(n)) + 1 equal bins. Fine for small, roughly normal data.
+    // https://en.wikipedia.org/wiki/Histogram#Sturges's_formula
+    Sturges,
+    // Bins of width 2 * IQR / cbrt(n). Holds up better with skewed data and outliers. Falls
+    // back to Sturges when the IQR is 0, and fails like FixedWidth if a few far outliers would
+    // need more than a million bins.
+    // https://en.wikipedia.org/wiki/Freedman%E2%80%93Diaconis_rule
+    FreedmanDiaconis,
+}
+
+// Counts of values falling in each bin. Every bin is [lower edge, upper edge) except the last,
+// which also includes its upper edge so the maximum lands in it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Histogram {
+    edges: Vec<f64>,
+    counts: Vec<usize>,
+    out_of_range: usize,
+}
+
+impl Histogram {
+    // Fails for an empty slice, a NaN anywhere in `v`, a non-positive or non-finite width, or
+    // fewer than two (or unsorted, or non-finite) edges. Infinities just land out of range with
+    // custom edges, but the other binnings need a finite min and max to place their edges.
+    pub fn new<T: Numeric>(v: &[T], binning: Binning) -> Result<Histogram, StatsError> {
+        Histogram::with_nan_policy(v, binning, NanPolicy::Error)
+    }
+
+    // Under Propagate, NaNs are counted in out_of_range, and the bins are placed using the other
+    // values.
+    pub fn with_nan_policy<T: Numeric>(
+        v: &[T],
+        binning: Binning,
+        nan: NanPolicy,
+    ) -> Result<Histogram, StatsError> {
+        let v = nan.filter(v)?;
+        check_not_empty(&v)?;
+
+        let mut histogram = Histogram::empty(bin_edges(&v, binning)?);
+        histogram.count(&v);
+        Ok(histogram)
+    }
+
+    // No counts yet. par_histogram counts chunks into these and merges them.
+    pub(crate) fn empty(edges: Vec<f64>) -> Histogram {
+        Histogram {
+            counts: vec![0; edges.len() - 1],
+            edges,
+            out_of_range: 0,
+        }
+    }
+
+    pub(crate) fn count<T: Numeric>(&mut self, v: &[T]) {
+        for x in v {
+            match bin_index(&self.edges, x.to_f64()) {
+                Some(i) => self.counts[i] += 1,
+                None => self.out_of_range += 1,
+            }
+        }
+    }
+
+    // Adds in the counts of a histogram with the same edges.
+    pub(crate) fn merge(&mut self, other: &Histogram) {
+        for (count, other_count) in self.counts.iter_mut().zip(&other.counts) {
+            *count += other_count;
+        }
+        self.out_of_range += other.out_of_range;
+    }
+
+    // The bin boundaries. There's one more edge than there are bins.
+    pub fn edges(&self) -> &[f64] {
+        &self.edges
+    }
+
+    pub fn counts(&self) -> &[usize] {
+        &self.counts
+    }
+
+    // Each bin's count plus every bin before it.
+    pub fn cumulative_counts(&self) -> Vec<usize> {
+        self.counts
+            .iter()
+            .scan(0, |total, &count| {
+                *total += count;
+                Some(*total)
+            })
+            .collect()
+    }
+
+    // (lower edge, upper edge, count) for each bin.
+    pub fn bins(&self) -> impl Iterator<Item = (f64, f64, usize)> + '_ {
+        self.edges
+            .windows(2)
+            .zip(&self.counts)
+            .map(|(pair, &count)| (pair[0], pair[1], count))
+    }
+
+    // Values that fell outside custom edges.
+    pub fn out_of_range(&self) -> usize {
+        self.out_of_range
+    }
+}
+
+// Checks custom edges, or places the edges for the other binnings from `v`, which isn't empty.
+pub(crate) fn bin_edges<T: Numeric>(v: &[T], binning: Binning) -> Result<Vec<f64>, StatsError> {
+    match binning {
+        Binning::Edges(edges) => {
+            if edges.len() < 2
+                || !edges.iter().all(|edge| edge.is_finite())
+                || !edges.windows(2).all(|pair| pair[0] < pair[1])
+            {
+                return Err(StatsError::InvalidBinning);
+            }
+            Ok(edges)
+        }
+        Binning::FixedWidth(width) => fixed_width_edges(v, width),
+        Binning::Sturges => sturges_edges(v),
+        Binning::FreedmanDiaconis => freedman_diaconis_edges(v),
+    }
+}
+
+fn bin_index(edges: &[f64], x: f64) -> Option<usize> {
+    let (&first, &last) = (edges.first()?, edges.last()?);
+    if !(first..=last).contains(&x) {
+        return None;
+    }
+
+    // The number of edges <= x, minus one, is the bin x starts in. The last edge belongs to the
+    // last bin.
+    let i = edges.partition_point(|&edge| edge <= x) - 1;
+    Some(i.min(edges.len() - 2))
+}
+
+// Ignores NaNs, which only get this far under Propagate.
+fn min_max<T: Numeric>(v: &[T]) -> Result<(f64, f64), StatsError> {
+    let min = super::min_with(v, NanPolicy::Skip)?.to_f64();
+    let max = super::max_with(v, NanPolicy::Skip)?.to_f64();
+    if !(min.is_finite() && max.is_finite()) {
+        return Err(StatsError::NonFinite);
+    }
+    Ok((min, max))
+}
+
+// `bins` equal bins from min to max. When every value is the same, one bin of width 1.
+fn even_edges(min: f64, max: f64, bins: usize) -> Vec<f64> {
+    if min == max {
+        return vec![min, min + 1.0];
+    }
+
+    let width = (max - min) / bins as f64;
+    (0..bins)
+        .map(|i| min + i as f64 * width)
+        .chain(std::iter::once(max))
+        .collect()
+}
+
+// Past this, a binning is almost certainly a mistake (a width in the wrong units, say), and the
+// edges alone would take megabytes.
+const MAX_BINS: f64 = 1e6;
+
+// Checks a bin count that was worked out in f64, before it's cast and used to size the edges.
+// A tiny width over a big range can make it huge, or infinite if the range overflows.
+fn checked_bins(bins: f64) -> Result<usize, StatsError> {
+    if bins.is_finite() && bins <= MAX_BINS {
+        Ok(bins as usize)
+    } else {
+        Err(StatsError::InvalidBinning)
+    }
+}
+
+fn fixed_width_edges<T: Numeric>(v: &[T], width: f64) -> Result<Vec<f64>, StatsError> {
+    if !(width.is_finite() && width > 0.0) {
+        return Err(StatsError::InvalidBinning);
+    }
+
+    let (min, max) = min_max(v)?;
+    let bins = checked_bins(((max - min) / width).floor() + 1.0)?;
+    Ok((0..=bins).map(|i| min + i as f64 * width).collect())
+}
+
+fn sturges_edges<T: Numeric>(v: &[T]) -> Result<Vec<f64>, StatsError> {
+    let (min, max) = min_max(v)?;
+    let bins = (v.len() as f64).log2().ceil() as usize + 1;
+    Ok(even_edges(min, max, bins))
+}
+
+fn freedman_diaconis_edges<T: Numeric>(v: &[T]) -> Result<Vec<f64>, StatsError> {
+    let (min, max) = min_max(v)?;
+    let quartiles = quantiles_with(v, &[0.25, 0.75], Interpolation::Linear, NanPolicy::Skip)?;
+    let iqr = quartiles[1] - quartiles[0];
+    if iqr <= 0.0 || min == max {
+        return sturges_edges(v);
+    }
+
+    let width = 2.0 * iqr / (v.len() as f64).cbrt();
+    let bins = checked_bins(((max - min) / width).ceil().max(1.0))?;
+    Ok(even_edges(min, max, bins))
+}
+
+// An ASCII bar chart, one `[lower, upper) | ##### count` line per bin.
+impl fmt::Display for Histogram {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let precision = f.precision().unwrap_or(2);
+        let last = self.counts.len() - 1;
+        let rows: Vec<(String, usize)> = self
+            .bins()
+            .enumerate()
+            .map(|(i, (lower, upper, count))| {
+                let close = if i == last { ']' } else { ')' };
+                (
+                    format!("[{lower:.precision$}, {upper:.precision$}{close}"),
+                    count,
+                )
+            })
+            .collect();
+        bar_chart(f, &rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frequency_table_is_sorted_with_cumulative_counts() {
+        let table = FrequencyTable::new(&[3, 1, 2, 3, 3, 1]);
+
+        assert_eq!(table.entries(), &[(1, 2), (2, 1), (3, 3)]);
+        assert_eq!(table.cumulative_counts(), vec![(1, 2), (2, 3), (3, 6)]);
+        assert_eq!(table.count(&3), 3);
+        assert_eq!(table.count(&7), 0);
+        assert_eq!(table.total(), 6);
+    }
+
+    #[test]
+    fn frequency_table_renders_bars() {
+        let table = FrequencyTable::new(&["b", "a", "b"]);
+
+        assert_eq!(
+            table.to_string(),
+            format!(
+                "\"a\" | {} 1\n\"b\" | {} 2\n",
+                "#".repeat(25),
+                "#".repeat(50)
+            )
+        );
+    }
+
+    #[test]
+    fn fixed_width_bins() {
+        let h = Histogram::new(&[0, 1, 2, 3, 4, 5], Binning::FixedWidth(2.0)).unwrap();
+
+        assert_eq!(h.edges(), &[0.0, 2.0, 4.0, 6.0]);
+        assert_eq!(h.counts(), &[2, 2, 2]);
+        assert_eq!(h.cumulative_counts(), vec![2, 4, 6]);
+    }
+
+    #[test]
+    fn custom_edges_count_out_of_range_values() {
+        let h =
+            Histogram::new(&[-5, 0, 1, 5, 10, 11], Binning::Edges(vec![0.0, 5.0, 10.0])).unwrap();
+
+        // The last bin includes its upper edge.
+        assert_eq!(h.counts(), &[2, 2]);
+        assert_eq!(h.out_of_range(), 2);
+        assert_eq!(
+            h.bins().collect::<Vec<_>>(),
+            vec![(0.0, 5.0, 2), (5.0, 10.0, 2)]
+        );
+    }
+
+    #[test]
+    fn sturges_bin_count() {
+        let v: Vec<i32> = (0..100).collect();
+        let h = Histogram::new(&v, Binning::Sturges).unwrap();
+
+        // ceil(log2(100)) + 1 = 8
+        assert_eq!(h.counts().len(), 8);
+        assert_eq!(h.counts().iter().sum::<usize>(), 100);
+        assert_eq!(h.edges()[0], 0.0);
+        assert_eq!(h.edges()[8], 99.0);
+    }
+
+    #[test]
+    fn freedman_diaconis_bin_width() {
+        let v: Vec<i32> = (0..1000).collect();
+        let h = Histogram::new(&v, Binning::FreedmanDiaconis).unwrap();
+
+        // IQR = 499.5, so width = 2 * 499.5 / 10 = 99.9 and 999 / 99.9 = 10 bins.
+        assert_eq!(h.counts().len(), 10);
+        assert_eq!(h.counts().iter().sum::<usize>(), 1000);
+    }
+
+    #[test]
+    fn freedman_diaconis_falls_back_to_sturges_without_spread() {
+        let v = [5, 5, 5, 5, 5, 5, 5, 100];
+
+        assert_eq!(
+            Histogram::new(&v, Binning::FreedmanDiaconis),
+            Histogram::new(&v, Binning::Sturges)
+        );
+    }
+
+    #[test]
+    fn all_equal_values_get_one_bin() {
+        let h = Histogram::new(&[7.5, 7.5], Binning::Sturges).unwrap();
+
+        assert_eq!(h.counts(), &[2]);
+    }
+
+    #[test]
+    fn invalid_binning_is_an_error() {
+        let v = [1, 2, 3];
+        let invalid = Err(StatsError::InvalidBinning);
+
+        assert_eq!(Histogram::new(&v, Binning::FixedWidth(0.0)), invalid);
+        assert_eq!(Histogram::new(&v, Binning::FixedWidth(f64::NAN)), invalid);
+        assert_eq!(Histogram::new(&v, Binning::Edges(vec![1.0])), invalid);
+        assert_eq!(Histogram::new(&v, Binning::Edges(vec![2.0, 1.0])), invalid);
+    }
+
+    #[test]
+    fn too_many_bins_is_an_error() {
+        let invalid = Err(StatsError::InvalidBinning);
+
+        assert_eq!(
+            Histogram::new(&[0.0, 1.0], Binning::FixedWidth(f64::MIN_POSITIVE)),
+            invalid
+        );
+        assert_eq!(
+            Histogram::new(&[0.0, 1.0], Binning::FixedWidth(1e-9)),
+            invalid
+        );
+        assert_eq!(
+            Histogram::new(&[-f64::MAX, f64::MAX], Binning::FixedWidth(1.0)),
+            invalid
+        );
+        assert!(Histogram::new(&[0.0, 1.0], Binning::FixedWidth(1e-5)).is_ok());
+
+        // A tight cluster and one far outlier: the IQR is tiny next to the range.
+        let mut v: Vec<f64> = (0..1000).map(|i| i as f64 * 1e-9).collect();
+        v.push(1e6);
+        assert_eq!(Histogram::new(&v, Binning::FreedmanDiaconis), invalid);
+    }
+
+    #[test]
+    fn bad_data_is_an_error() {
+        assert_eq!(
+            Histogram::new::<i32>(&[], Binning::Sturges),
+            Err(StatsError::Empty)
+        );
+        assert_eq!(
+            Histogram::new(&[1.0, f64::NAN], Binning::Sturges),
+            Err(StatsError::NonFinite)
+        );
+        assert_eq!(
+            Histogram::new(&[1.0, f64::INFINITY], Binning::FreedmanDiaconis),
+            Err(StatsError::NonFinite)
+        );
+
+        let h = Histogram::new(&[1.0, f64::INFINITY], Binning::Edges(vec![0.0, 2.0])).unwrap();
+        assert_eq!(h.out_of_range(), 1);
+    }
+
+    #[test]
+    fn nan_policy_is_honoured() {
+        let v = [0.5, f64::NAN, 1.5, 1.5];
+
+        let skipped = Histogram::with_nan_policy(&v, Binning::FixedWidth(1.0), NanPolicy::Skip);
+        assert_eq!(skipped.unwrap().counts(), &[1, 2]);
+
+        let propagated =
+            Histogram::with_nan_policy(&v, Binning::FixedWidth(1.0), NanPolicy::Propagate).unwrap();
+        assert_eq!(propagated.counts(), &[1, 2]);
+        assert_eq!(propagated.out_of_range(), 1);
+
+        assert_eq!(
+            Histogram::with_nan_policy(&[f64::NAN], Binning::Sturges, NanPolicy::Propagate),
+            Err(StatsError::Empty)
+        );
+    }
+
+    #[test]
+    fn histogram_renders_bars() {
+        let h = Histogram::new(&[0, 1, 1, 3], Binning::FixedWidth(2.0)).unwrap();
+
+        assert_eq!(
+            format!("{h:.1}"),
+            format!(
+                "[0.0, 2.0) | {} 3\n[2.0, 4.0] | {} 1\n",
+                "#".repeat(50),
+                "#".repeat(16)
+            )
+        );
+    }
+}
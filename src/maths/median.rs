@@ -0,0 +1,292 @@
+use std::fmt;
+
+use super::error::check_not_empty;
+use super::{
+    quantile_in_place, quantile_with, Integer, Interpolation, NanPolicy, Numeric, StatsError,
+};
+
+// Returns an f64 for every input type. That's exact for all 32-bit integers (including the
+// half in an even-length median) and the natural answer for floats. 64-bit integers past 2^53
+// get rounded; median_exact gives those an exact answer.
+pub fn median<T: Numeric>(v: &[T]) -> Result<f64, StatsError> {
+    median_in_place(&mut v.to_vec())
+}
+
+// median with a choice of what to do about NaNs, e.g. Skip for measurements with gaps in them.
+pub fn median_with<T: Numeric>(v: &[T], nan: NanPolicy) -> Result<f64, StatsError> {
+    quantile_with(v, 0.5, Interpolation::Midpoint, nan)
+}
+
+// Same as median, but works on the caller's slice instead of a copy. The slice is left
+// partially reordered around the middle.
+pub fn median_in_place<T: Numeric>(v: &mut [T]) -> Result<f64, StatsError> {
+    // The median is the 50th percentile. With h = (n - 1) / 2, Midpoint averages the two middle
+    // values when n is even and reads the single middle value when n is odd.
+    quantile_in_place(v, 0.5, Interpolation::Midpoint)
+}
+
+// The exact median of integers, kept as the two middle values (the same value twice for an
+// odd-length input) rather than as their average, so nothing is lost to rounding or overflow.
+pub fn median_exact<T: Integer>(v: &[T]) -> Result<Midpoint<T>, StatsError> {
+    median_exact_in_place(&mut v.to_vec())
+}
+
+pub fn median_exact_in_place<T: Integer>(v: &mut [T]) -> Result<Midpoint<T>, StatsError> {
+    check_not_empty(v)?;
+
+    // For an even length, the other middle value is the largest one left of the selected one
+    // (and there's at least one value there).
+    let len = v.len();
+    let (lower, &mut hi, _) = v.select_nth_unstable(len / 2);
+    let lo = match len % 2 {
+        0 => lower.iter().copied().max().unwrap_or(hi),
+        _ => hi,
+    };
+
+    Ok(Midpoint { lo, hi })
+}
+
+// (lo + hi) / 2, computed exactly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Midpoint<T> {
+    pub lo: T,
+    pub hi: T,
+}
+
+impl<T: Integer> Midpoint<T> {
+    // The whole part, rounded toward zero like f64::trunc.
+    pub fn trunc(&self) -> T {
+        self.lo.half_sum(self.hi).0
+    }
+
+    // False when the midpoint lands on a half.
+    pub fn is_whole(&self) -> bool {
+        self.lo.half_sum(self.hi).1 == 0
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        let (whole, remainder) = self.lo.half_sum(self.hi);
+        whole.to_f64() + remainder as f64 / 2.0
+    }
+}
+
+// Prints the exact decimal value, e.g. 2147483646.5 or -0.5.
+impl<T: Integer + fmt::Display> fmt::Display for Midpoint<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.lo.half_sum(self.hi) {
+            (whole, 0) => write!(f, "{whole}"),
+            // Rounding toward zero means a -1 remainder only comes with a whole part <= 0, so the
+            // only time the sign isn't already printed is -0.5.
+            (whole, -1) if whole.to_f64() == 0.0 => write!(f, "-0.5"),
+            (whole, _) => write!(f, "{whole}.5"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maths;
+
+    #[test]
+    fn median_works_with_odd_num_elements() {
+        let v = vec![3, 2, 2, 2, 1];
+        let expected = 2.0;
+
+        assert_eq!(median(&v).unwrap(), expected);
+    }
+
+    #[test]
+    fn median_works_with_even_num_elements_same_middle_values() {
+        let v = vec![3, 2, 2, 1];
+        let expected = 2.0;
+
+        assert_eq!(median(&v).unwrap(), expected);
+    }
+
+    #[test]
+    fn median_works_with_even_num_elements_different_middle_values() {
+        let v = vec![4, 3, 2, 1];
+        let expected = 2.5;
+
+        assert_eq!(median(&v).unwrap(), expected);
+    }
+
+    #[test]
+    fn median_works_with_one_element_vector() {
+        let v = vec![1];
+        let expected = 1.0;
+
+        assert_eq!(median(&v).unwrap(), expected);
+    }
+
+    #[test]
+    fn median_works_with_two_element_vector() {
+        let v = vec![1, 10];
+        let expected = 5.5;
+
+        assert_eq!(median(&v).unwrap(), expected);
+    }
+
+    #[test]
+    fn median_returns_empty_error_with_empty_vector() {
+        assert_eq!(median::<i32>(&[]), Err(StatsError::Empty));
+    }
+
+    #[test]
+    fn median_rejects_nan() {
+        assert_eq!(median(&[1.0, f64::NAN, 2.0]), Err(StatsError::NonFinite));
+    }
+
+    #[test]
+    fn median_between_opposite_infinities_is_an_error() {
+        let (inf, neg_inf) = (f64::INFINITY, f64::NEG_INFINITY);
+
+        assert_eq!(median(&[neg_inf, inf]), Err(StatsError::NonFinite));
+        assert_eq!(median(&[neg_inf, 1.0, inf]), Ok(1.0));
+        assert_eq!(median(&[inf, inf]), Ok(inf));
+    }
+
+    #[test]
+    fn median_with_skips_or_propagates_nan() {
+        let v = [1.0, f64::NAN, 2.0, 4.0];
+
+        assert_eq!(median_with(&v, NanPolicy::Skip), Ok(2.0));
+        assert!(median_with(&v, NanPolicy::Propagate).unwrap().is_nan());
+        assert_eq!(median_with(&v, NanPolicy::Error), median(&v));
+    }
+
+    #[test]
+    fn median_works_with_unsigned_and_wide_integers() {
+        let counters: Vec<u64> = vec![30, 10, 20];
+        let timestamps: Vec<i64> = vec![-5, 5, 10, -10];
+
+        assert_eq!(median(&counters).unwrap(), 20.0);
+        assert_eq!(median(&timestamps).unwrap(), 0.0);
+    }
+
+    #[test]
+    fn median_works_with_floats() {
+        let v = vec![0.5, -1.25, 3.0, 2.0];
+
+        assert_eq!(median(&v).unwrap(), 1.25);
+    }
+
+    #[test]
+    fn median_in_place_works_without_copying() {
+        let mut v = vec![9, 1, 8, 2, 7, 3];
+
+        assert_eq!(median_in_place(&mut v).unwrap(), 5.0);
+        // Everything left of the upper middle value is no bigger than it.
+        assert!(v[..3].iter().all(|&x| x <= v[3]));
+    }
+
+    #[test]
+    fn median_in_place_returns_empty_error_with_empty_slice() {
+        assert_eq!(median_in_place::<i32>(&mut []), Err(StatsError::Empty));
+    }
+
+    #[test]
+    fn median_matches_sorting_on_many_inputs() {
+        // Deterministic pseudo-random inputs of every length from 1 to 200, with lots of
+        // duplicates so ties around the middle get exercised too.
+        let mut rng = maths::Rng::new(12345);
+        for len in 1..=200 {
+            let v: Vec<i32> = (0..len).map(|_| rng.below(50) as i32).collect();
+
+            let mut sorted = v.clone();
+            sorted.sort();
+            let expected = if len % 2 == 0 {
+                (sorted[len / 2 - 1] + sorted[len / 2]) as f64 / 2.0
+            } else {
+                sorted[len / 2] as f64
+            };
+
+            assert_eq!(median(&v).unwrap(), expected, "{v:?}");
+        }
+    }
+
+    #[test]
+    fn median_works_with_i32_extremes() {
+        assert_eq!(median(&[i32::MAX, i32::MAX]).unwrap(), i32::MAX as f64);
+        assert_eq!(median(&[i32::MIN, i32::MIN]).unwrap(), i32::MIN as f64);
+        assert_eq!(median(&[i32::MIN, i32::MAX]).unwrap(), -0.5);
+        assert_eq!(median(&[i32::MAX - 1, i32::MAX]).unwrap(), 2_147_483_646.5);
+    }
+
+    #[test]
+    fn median_is_exact_past_f32_precision() {
+        // 2^24 + 1 can't be represented as an f32.
+        let v = vec![16_777_217, 16_777_219];
+
+        assert_eq!(median(&v).unwrap(), 16_777_218.0);
+    }
+
+    #[test]
+    fn median_does_not_overflow_with_huge_floats() {
+        assert_eq!(median(&[f64::MAX, f64::MAX]).unwrap(), f64::MAX);
+    }
+
+    #[test]
+    fn median_exact_works_with_i32_extremes() {
+        let both_max = median_exact(&[i32::MAX, i32::MAX]).unwrap();
+        assert_eq!(both_max.trunc(), i32::MAX);
+        assert!(both_max.is_whole());
+
+        let both_min = median_exact(&[i32::MIN, i32::MIN]).unwrap();
+        assert_eq!(both_min.trunc(), i32::MIN);
+
+        let min_and_max = median_exact(&[i32::MAX, i32::MIN]).unwrap();
+        assert_eq!(
+            min_and_max,
+            Midpoint {
+                lo: i32::MIN,
+                hi: i32::MAX
+            }
+        );
+        assert_eq!(min_and_max.trunc(), 0);
+        assert!(!min_and_max.is_whole());
+        assert_eq!(min_and_max.to_f64(), -0.5);
+        assert_eq!(min_and_max.to_string(), "-0.5");
+
+        let near_max = median_exact(&[i32::MAX, i32::MAX - 1, 0]).unwrap();
+        assert_eq!(near_max.to_string(), "2147483646");
+        let near_max = median_exact(&[i32::MAX, i32::MAX - 1]).unwrap();
+        assert_eq!(near_max.to_string(), "2147483646.5");
+    }
+
+    #[test]
+    fn median_exact_works_past_f64_precision() {
+        // f64 can't tell these apart from their neighbours.
+        let v: Vec<i64> = vec![i64::MAX, i64::MAX - 2, 0, i64::MAX - 1];
+        let m = median_exact(&v).unwrap();
+
+        assert_eq!(m.trunc(), i64::MAX - 2);
+        assert_eq!(m.to_string(), "9223372036854775805.5");
+
+        let v: Vec<u64> = vec![u64::MAX, u64::MAX - 1];
+        assert_eq!(
+            median_exact(&v).unwrap().to_string(),
+            "18446744073709551614.5"
+        );
+    }
+
+    #[test]
+    fn median_exact_prints_negative_halves() {
+        assert_eq!(median_exact(&[-3, -4]).unwrap().to_string(), "-3.5");
+        assert_eq!(median_exact(&[-1, 0]).unwrap().to_string(), "-0.5");
+        assert_eq!(median_exact(&[0, 1]).unwrap().to_string(), "0.5");
+    }
+
+    #[test]
+    fn median_exact_agrees_with_median_on_small_integers() {
+        let v = vec![4, 3, 2, 1];
+
+        assert_eq!(median_exact(&v).unwrap().to_f64(), median(&v).unwrap());
+    }
+
+    #[test]
+    fn median_exact_returns_empty_error_with_empty_vector() {
+        assert_eq!(median_exact::<i64>(&[]), Err(StatsError::Empty));
+    }
+}
@@ -0,0 +1,68 @@
+// Statistics over slices of numbers. These started out as the i32-only helpers in the vectors
+// binary and are generic over the Numeric trait so they also work on u64 counters, i64
+// timestamps and f64 measurements.
+mod bootstrap;
+mod correlation;
+mod error;
+mod heavy_hitters;
+mod histogram;
+mod median;
+mod mode;
+mod nan;
+mod numeric;
+mod outliers;
+mod parallel;
+mod quantile;
+mod rng;
+mod running_median;
+mod running_stats;
+mod sketch;
+mod smoothing;
+mod summary;
+mod weighted;
+
+pub use bootstrap::{bootstrap, ConfidenceInterval};
+pub use correlation::{
+    linear_regression, linear_regression_with, pearson_correlation, pearson_correlation_with,
+    population_covariance, population_covariance_with, ranks, sample_covariance,
+    sample_covariance_with, spearman_correlation, spearman_correlation_with, LinearRegression,
+};
+pub use error::StatsError;
+pub use heavy_hitters::{CountBounds, CountMinSketch, MisraGries};
+pub use histogram::{Binning, FrequencyTable, Histogram};
+pub use median::{
+    median, median_exact, median_exact_in_place, median_in_place, median_with, Midpoint,
+};
+pub use mode::{count_values, mode_n, mode_n_lgn, mode_n_lgn_with, mode_n_with, Modes};
+pub use nan::NanPolicy;
+pub use numeric::{Integer, Numeric, TotalOrd};
+pub use outliers::{
+    median_absolute_deviation, median_absolute_deviation_with, outlier_fences, outlier_fences_with,
+    outliers, outliers_with, trimmed_mean, trimmed_mean_with, winsorize, winsorize_with,
+    OutlierMethod,
+};
+pub use parallel::{
+    available_threads, par_describe, par_describe_with, par_histogram, par_histogram_with, par_mode,
+};
+pub use quantile::{
+    quantile, quantile_in_place, quantile_with, quantiles, quantiles_with, Interpolation,
+};
+pub use rng::Rng;
+pub use running_median::{RunningMedian, SlidingMedian};
+pub use running_stats::RunningStats;
+pub use sketch::KllSketch;
+pub use smoothing::{
+    cumulative_sum, exponential_moving_average, moving_average, weighted_moving_average,
+    CumulativeSum, ExponentialMovingAverage, MovingAverage, WeightedMovingAverage,
+};
+pub use summary::{
+    describe, describe_with, max, max_with, mean, mean_with, min, min_with, population_stddev,
+    population_stddev_with, population_variance, population_variance_with, range, range_with,
+    sample_stddev, sample_stddev_with, sample_variance, sample_variance_with, sum, sum_with,
+    Summary,
+};
+pub use weighted::{
+    weighted_mean, weighted_mean_with, weighted_median, weighted_median_with, weighted_mode,
+    weighted_mode_with, weighted_quantile, weighted_quantile_with, weighted_quantiles,
+    weighted_quantiles_with,
+};
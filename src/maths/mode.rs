@@ -0,0 +1,232 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::ops::Range;
+
+use super::nan::first_nan;
+use super::{NanPolicy, Numeric, StatsError, TotalOrd};
+
+// How many times each distinct value shows up. FrequencyTable is the sorted, printable version.
+pub fn count_values<T: Hash + Eq>(v: &[T]) -> HashMap<&T, usize> {
+    let mut value_counts = HashMap::new();
+    for val in v {
+        let count = value_counts.entry(val).or_insert(0);
+        *count += 1;
+    }
+
+    value_counts
+}
+
+// The most frequent values, in ascending order, and how many times each of them shows up.
+// Sorted so the result is the same from run to run, whatever order the HashMap counted them in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Modes<T> {
+    pub values: Vec<T>,
+    pub count: usize,
+}
+
+impl<T: Ord + Clone> Modes<T> {
+    // The values tied for the highest count. Values with a count of 0 are ignored, and Empty if
+    // that leaves nothing.
+    pub(crate) fn from_counts<'a, I>(counts: I) -> Result<Modes<T>, StatsError>
+    where
+        I: IntoIterator<Item = (&'a T, usize)>,
+        T: 'a,
+    {
+        let mut modes = Modes {
+            values: vec![],
+            count: 0,
+        };
+        for (x, count) in counts {
+            if count > modes.count {
+                modes.values.clear();
+                modes.count = count;
+            }
+            if count > 0 && count == modes.count {
+                modes.values.push(x.clone());
+            }
+        }
+
+        if modes.values.is_empty() {
+            return Err(StatsError::Empty);
+        }
+        modes.values.sort();
+        Ok(modes)
+    }
+}
+
+// Sorts a copy instead of hashing, so T only needs Ord.
+pub fn mode_n_lgn<T: Ord + Clone>(v: &[T]) -> Result<Modes<T>, StatsError> {
+    let mut sorted = v.to_vec();
+    sorted.sort();
+
+    let count = equal_runs(&sorted, T::eq)
+        .map(|run| run.len())
+        .max()
+        .ok_or(StatsError::Empty)?;
+    Ok(Modes {
+        // Runs come in ascending order, so the modes do too.
+        values: equal_runs(&sorted, T::eq)
+            .filter(|run| run.len() == count)
+            .map(|run| sorted[run.start].clone())
+            .collect(),
+        count,
+    })
+}
+
+// The index ranges of the runs of equal values in `sorted`, in order. mode_n_lgn and ranks both
+// sort and then walk these.
+pub(crate) fn equal_runs<'a, T, F>(
+    sorted: &'a [T],
+    same: F,
+) -> impl Iterator<Item = Range<usize>> + 'a
+where
+    F: FnMut(&T, &T) -> bool + 'a,
+{
+    let mut start = 0;
+    sorted.chunk_by(same).map(move |run| {
+        let range = start..start + run.len();
+        start = range.end;
+        range
+    })
+}
+
+pub fn mode_n<T: Hash + Ord + Clone>(v: &[T]) -> Result<Modes<T>, StatsError> {
+    Modes::from_counts(count_values(v))
+}
+
+// The modes of floats (or any Numeric), which mode_n can't take directly because f64 isn't Hash.
+// Values are compared by their total order, so 0.0 and -0.0 are counted separately. Under
+// Propagate a NaN anywhere makes the mode just that NaN, with the number of NaNs as the count.
+pub fn mode_n_with<T: Numeric>(v: &[T], nan: NanPolicy) -> Result<Modes<T>, StatsError> {
+    numeric_modes(v, nan, mode_n)
+}
+
+// Same as mode_n_with, by sorting like mode_n_lgn.
+pub fn mode_n_lgn_with<T: Numeric>(v: &[T], nan: NanPolicy) -> Result<Modes<T>, StatsError> {
+    numeric_modes(v, nan, mode_n_lgn)
+}
+
+fn numeric_modes<T, F>(v: &[T], nan: NanPolicy, modes_of: F) -> Result<Modes<T>, StatsError>
+where
+    T: Numeric,
+    F: Fn(&[TotalOrd<T>]) -> Result<Modes<TotalOrd<T>>, StatsError>,
+{
+    let v = nan.filter(v)?;
+    if let Some(nan) = first_nan(&v) {
+        return Ok(Modes {
+            values: vec![nan],
+            count: v.iter().filter(|x| x.is_nan()).count(),
+        });
+    }
+
+    let wrapped: Vec<TotalOrd<T>> = v.iter().copied().map(TotalOrd).collect();
+    let modes = modes_of(&wrapped)?;
+    Ok(Modes {
+        values: modes.values.into_iter().map(|TotalOrd(x)| x).collect(),
+        count: modes.count,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn modes<T>(values: Vec<T>, count: usize) -> Modes<T> {
+        Modes { values, count }
+    }
+
+    #[test]
+    fn mode_n_works_one_mode() {
+        let v = vec![1, -2, 3, 3, 4, -2, 3];
+
+        assert_eq!(mode_n(&v).unwrap(), modes(vec![3], 3));
+    }
+
+    #[test]
+    fn mode_n_works_many_modes() {
+        let v = vec![5, 1, 4, 2, 3];
+
+        assert_eq!(mode_n(&v).unwrap(), modes(vec![1, 2, 3, 4, 5], 1));
+    }
+
+    #[test]
+    fn mode_n_returns_empty_error_with_empty_vector() {
+        assert_eq!(mode_n::<i32>(&[]), Err(StatsError::Empty));
+        assert_eq!(mode_n_lgn::<i32>(&[]), Err(StatsError::Empty));
+    }
+
+    #[test]
+    fn mode_n_lgn_matches_mode_n() {
+        let v = vec![1, 2, 3, 3, 4, 2, 3, 4, 4];
+
+        assert_eq!(mode_n_lgn(&v), mode_n(&v));
+        assert_eq!(mode_n_lgn(&v).unwrap(), modes(vec![3, 4], 3));
+    }
+
+    #[test]
+    fn ties_come_back_in_the_same_order_every_time() {
+        let v: Vec<i32> = (0..100).rev().chain(0..100).collect();
+        let expected = modes((0..100).collect(), 2);
+
+        // Each HashMap gets its own random seed, so repeating this exercises different
+        // iteration orders.
+        for _ in 0..10 {
+            assert_eq!(mode_n(&v).unwrap(), expected);
+            assert_eq!(mode_n_lgn(&v).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn mode_n_works_with_other_hashable_types() {
+        let counters: Vec<u64> = vec![u64::MAX, 7, u64::MAX];
+        let words = ["b", "a", "b", "a", "c"];
+
+        assert_eq!(mode_n(&counters).unwrap(), modes(vec![u64::MAX], 2));
+        assert_eq!(mode_n_lgn(&words).unwrap(), modes(vec!["a", "b"], 2));
+    }
+
+    #[test]
+    fn count_values_counts_each_distinct_value() {
+        let v = vec![1, -2, 3, 3, 4, -2, 3];
+
+        assert_eq!(
+            count_values(&v),
+            HashMap::from([(&1, 1), (&-2, 2), (&3, 3), (&4, 1)])
+        );
+        assert!(count_values::<i32>(&[]).is_empty());
+    }
+
+    #[test]
+    fn mode_n_with_works_on_floats() {
+        let v = [0.5, f64::NAN, 2.5, 0.5, f64::NAN, f64::NAN, -0.0, 0.0];
+
+        assert_eq!(
+            mode_n_with(&v, NanPolicy::Skip).unwrap(),
+            modes(vec![0.5], 2)
+        );
+        assert_eq!(
+            mode_n_with(&v, NanPolicy::Error),
+            Err(StatsError::NonFinite)
+        );
+
+        let propagated = mode_n_with(&v, NanPolicy::Propagate).unwrap();
+        assert_eq!(propagated.count, 3);
+        assert!(propagated.values.len() == 1 && propagated.values[0].is_nan());
+
+        let signed_zeros = mode_n_with(&[0.0f64, -0.0, 1.0], NanPolicy::Error).unwrap();
+        assert_eq!(signed_zeros.values.len(), 3);
+        assert!(signed_zeros.values[0].is_sign_negative());
+    }
+
+    #[test]
+    fn mode_n_lgn_with_matches_mode_n_with() {
+        let v = [0.5, f64::NAN, 2.5, 0.5, f64::NAN, f64::NAN, -0.0, 0.0, 2.5];
+
+        for nan in [NanPolicy::Skip, NanPolicy::Error] {
+            assert_eq!(mode_n_lgn_with(&v, nan), mode_n_with(&v, nan));
+        }
+        let propagated = mode_n_lgn_with(&v, NanPolicy::Propagate).unwrap();
+        assert_eq!(propagated.count, 3);
+        assert!(propagated.values[0].is_nan());
+    }
+}
@@ -0,0 +1,99 @@
+use std::borrow::Cow;
+
+use super::{Numeric, StatsError};
+
+// What the *_with functions do when float input has NaNs in it. The plain functions (median,
+// mean, ...) use Error, and so do the *_in_place ones. The streaming structures (RunningStats,
+// RunningMedian, SlidingMedian, KllSketch) take a policy in with_nan_policy and use Error from
+// new. Integers never have NaNs, so the policy makes no difference for them.
+//
+// Only NaN is special here. Infinities are ordinary values to the order statistics (median,
+// min, quantiles), and for the arithmetic ones (mean, variance) an infinite input is reported as
+// NonFinite, unless the policy is Propagate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NanPolicy {
+    // Any NaN makes the result NaN, the way IEEE arithmetic does. Non-finite results of the
+    // arithmetic statistics are returned as they are instead of as NonFinite errors.
+    Propagate,
+    // Drop the NaNs and compute the statistic over what's left. All-NaN input is Empty.
+    Skip,
+    // Any NaN is StatsError::NonFinite.
+    #[default]
+    Error,
+}
+
+// x and y, as NanPolicy::filter_pairs leaves them.
+pub(crate) type Pairs<'a, X, Y> = (Cow<'a, [X]>, Cow<'a, [Y]>);
+
+impl NanPolicy {
+    // The values a statistic should be computed over: `v` itself, or a copy without the NaNs for
+    // Skip. Only copies when there's a NaN to drop.
+    pub(crate) fn filter<T: Numeric>(self, v: &[T]) -> Result<Cow<'_, [T]>, StatsError> {
+        if !v.iter().any(|x| x.is_nan()) {
+            return Ok(Cow::Borrowed(v));
+        }
+
+        match self {
+            NanPolicy::Propagate => Ok(Cow::Borrowed(v)),
+            NanPolicy::Skip => Ok(Cow::Owned(
+                v.iter().copied().filter(|x| !x.is_nan()).collect(),
+            )),
+            NanPolicy::Error => Err(StatsError::NonFinite),
+        }
+    }
+
+    // filter for two series measured together: under Skip, a pair is dropped if either half of
+    // it is a NaN, so x and y stay lined up.
+    pub(crate) fn filter_pairs<'a, X: Numeric, Y: Numeric>(
+        self,
+        x: &'a [X],
+        y: &'a [Y],
+    ) -> Result<Pairs<'a, X, Y>, StatsError> {
+        if self != NanPolicy::Skip {
+            return Ok((self.filter(x)?, self.filter(y)?));
+        }
+        if !x.iter().any(|a| a.is_nan()) && !y.iter().any(|b| b.is_nan()) {
+            return Ok((Cow::Borrowed(x), Cow::Borrowed(y)));
+        }
+
+        let (x, y) = x
+            .iter()
+            .zip(y)
+            .filter(|(a, b)| !a.is_nan() && !b.is_nan())
+            .map(|(&a, &b)| (a, b))
+            .unzip();
+        Ok((Cow::Owned(x), Cow::Owned(y)))
+    }
+}
+
+// The first NaN in `v`. After NanPolicy::filter, there can only be one under Propagate.
+pub(crate) fn first_nan<T: Numeric>(v: &[T]) -> Option<T> {
+    v.iter().copied().find(|x| x.is_nan())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filter_only_copies_when_dropping_nans() {
+        let clean = [1.0, 2.0];
+        let dirty = [1.0, f64::NAN, 2.0];
+
+        assert!(matches!(
+            NanPolicy::Skip.filter(&clean),
+            Ok(Cow::Borrowed(_))
+        ));
+        assert_eq!(
+            NanPolicy::Skip.filter(&dirty).unwrap().as_ref(),
+            &[1.0, 2.0]
+        );
+        assert_eq!(NanPolicy::Propagate.filter(&dirty).unwrap().len(), 3);
+        assert_eq!(NanPolicy::Error.filter(&dirty), Err(StatsError::NonFinite));
+    }
+
+    #[test]
+    fn default_is_error() {
+        assert_eq!(NanPolicy::default(), NanPolicy::Error);
+    }
+}
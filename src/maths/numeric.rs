@@ -0,0 +1,230 @@
+use std::cmp::Ordering;
+use std::fmt::Debug;
+use std::hash::{Hash, Hasher};
+use std::ops::{Add, Sub};
+
+// The numeric types the statistics functions accept. Integers and floats are both Copy and
+// PartialOrd, but floats aren't Ord (NaN) and integers don't convert to f64 the same way, so
+// each type says how to do both.
+pub trait Numeric: Copy + PartialOrd + Debug {
+    // Every 32-bit integer (and the sum of two of them) fits exactly in an f64's 53-bit
+    // mantissa, so this is lossless for anything up to i32/u32. 64-bit integers above 2^53 round.
+    fn to_f64(self) -> f64;
+
+    // A total order for sorting. Integers use Ord; floats use f64::total_cmp, which puts
+    // -NaN first and NaN last instead of panicking or leaving the slice half sorted.
+    fn total_cmp(&self, other: &Self) -> Ordering;
+
+    // A hash that agrees with total_cmp: values that compare equal hash the same. Floats hash
+    // their bits, which total_cmp also tells apart (0.0 isn't -0.0, and NaNs with different
+    // payloads are different values).
+    fn total_hash<H: Hasher>(&self, state: &mut H);
+
+    // A type sums and ranges can be accumulated in without overflowing: i128 for signed integers
+    // up to 64 bits (2^64 values of i64::MAX still fit), u128 for unsigned ones, and f64 for
+    // floats and the 128-bit integers, which have nothing wider to go to.
+    type Wide: Copy
+        + Debug
+        + PartialEq
+        + PartialOrd
+        + Default
+        + Add<Output = Self::Wide>
+        + Sub<Output = Self::Wide>;
+
+    fn widen(self) -> Self::Wide;
+
+    fn wide_to_f64(wide: Self::Wide) -> f64;
+
+    // Whether adding and subtracting in Wide is exact, so a running total never drifts however
+    // long it's kept up. False when Wide is f64, i.e. for floats and 128-bit integers.
+    const EXACT_WIDE: bool;
+
+    // Always false for integers.
+    fn is_nan(self) -> bool {
+        self.to_f64().is_nan()
+    }
+
+    // Always true for integers.
+    fn is_finite(self) -> bool {
+        self.to_f64().is_finite()
+    }
+}
+
+macro_rules! impl_numeric_for_int {
+    ($($t:ty => $wide:ty),*) => {
+        $(
+            impl Numeric for $t {
+                type Wide = $wide;
+
+                // Only the 128-bit integers have to fall back to an f64 Wide.
+                const EXACT_WIDE: bool = std::mem::size_of::<$t>() < 16;
+
+                fn to_f64(self) -> f64 {
+                    self as f64
+                }
+
+                fn total_cmp(&self, other: &Self) -> Ordering {
+                    self.cmp(other)
+                }
+
+                fn total_hash<H: Hasher>(&self, state: &mut H) {
+                    self.hash(state)
+                }
+
+                fn widen(self) -> Self::Wide {
+                    self as $wide
+                }
+
+                fn wide_to_f64(wide: Self::Wide) -> f64 {
+                    wide as f64
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! impl_numeric_for_float {
+    ($($t:ty),*) => {
+        $(
+            impl Numeric for $t {
+                type Wide = f64;
+
+                const EXACT_WIDE: bool = false;
+
+                fn to_f64(self) -> f64 {
+                    self as f64
+                }
+
+                fn total_cmp(&self, other: &Self) -> Ordering {
+                    <$t>::total_cmp(self, other)
+                }
+
+                fn total_hash<H: Hasher>(&self, state: &mut H) {
+                    self.to_bits().hash(state)
+                }
+
+                fn widen(self) -> Self::Wide {
+                    self as f64
+                }
+
+                fn wide_to_f64(wide: Self::Wide) -> f64 {
+                    wide
+                }
+            }
+        )*
+    };
+}
+
+impl_numeric_for_int!(
+    i8 => i128, i16 => i128, i32 => i128, i64 => i128, isize => i128, i128 => f64,
+    u8 => u128, u16 => u128, u32 => u128, u64 => u128, usize => u128, u128 => f64
+);
+impl_numeric_for_float!(f32, f64);
+
+// Integers get an exact median. The even-length median of integers is always either a whole
+// number or a whole number and a half, so it can be stored without rounding as long as computing
+// it doesn't overflow.
+pub trait Integer: Numeric + Ord {
+    // (self + other) / 2 without overflowing, as a whole part rounded toward zero and a remainder
+    // of -1, 0 or 1 halves. I.e. (self + other) == 2 * whole + remainder.
+    fn half_sum(self, other: Self) -> (Self, i8);
+}
+
+macro_rules! impl_integer_for_signed {
+    ($($t:ty),*) => {
+        $(
+            impl Integer for $t {
+                fn half_sum(self, other: Self) -> (Self, i8) {
+                    // midpoint rounds toward zero for signed integers. The whole part lies between
+                    // self and other, so the wrapping differences below never actually wrap by the
+                    // time they're summed back up to -1, 0 or 1.
+                    let whole = self.midpoint(other);
+                    let remainder = self.wrapping_sub(whole).wrapping_add(other.wrapping_sub(whole));
+                    (whole, remainder as i8)
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! impl_integer_for_unsigned {
+    ($($t:ty),*) => {
+        $(
+            impl Integer for $t {
+                fn half_sum(self, other: Self) -> (Self, i8) {
+                    // Unsigned midpoint rounds down, so the remainder is only ever 0 or 1.
+                    let whole = self.midpoint(other);
+                    (whole, ((self ^ other) & 1) as i8)
+                }
+            }
+        )*
+    };
+}
+
+impl_integer_for_signed!(i8, i16, i32, i64, i128, isize);
+impl_integer_for_unsigned!(u8, u16, u32, u64, u128, usize);
+
+// Gives any Numeric value a real Ord and Hash (by Numeric::total_cmp and total_hash) so floats
+// can go in BinaryHeaps, BTreeMaps and HashMaps, and be sorted or counted by mode_n and
+// FrequencyTable like integers. NaN is an ordinary value here, ordered after +infinity.
+#[derive(Debug, Clone, Copy)]
+pub struct TotalOrd<T>(pub T);
+
+impl<T: Numeric> PartialEq for TotalOrd<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.total_cmp(&other.0).is_eq()
+    }
+}
+
+impl<T: Numeric> Eq for TotalOrd<T> {}
+
+impl<T: Numeric> Hash for TotalOrd<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.total_hash(state)
+    }
+}
+
+impl<T: Numeric> PartialOrd for TotalOrd<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T: Numeric> Ord for TotalOrd<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+impl<T> From<T> for TotalOrd<T> {
+    fn from(x: T) -> Self {
+        TotalOrd(x)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn total_ord_orders_and_hashes_floats() {
+        let mut v: Vec<TotalOrd<f64>> = [2.0, f64::NAN, -0.0, 0.0, f64::NEG_INFINITY]
+            .into_iter()
+            .map(TotalOrd)
+            .collect();
+        v.sort();
+
+        let sorted: Vec<f64> = v.iter().map(|x| x.0).collect();
+        assert_eq!(sorted[..4], [f64::NEG_INFINITY, -0.0, 0.0, 2.0]);
+        assert!(sorted[4].is_nan());
+        assert!(sorted[1].is_sign_negative());
+
+        let set: HashSet<TotalOrd<f64>> = [1.5, 1.5, f64::NAN, f64::NAN, 0.0, -0.0]
+            .into_iter()
+            .map(TotalOrd)
+            .collect();
+        assert_eq!(set.len(), 4);
+        assert!(set.contains(&TotalOrd(f64::NAN)));
+    }
+}
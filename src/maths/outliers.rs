@@ -0,0 +1,344 @@
+use super::error::check_not_empty;
+use super::nan::first_nan;
+use super::{
+    mean_with, median_with, population_stddev_with, quantiles_with, Interpolation, NanPolicy,
+    Numeric, StatsError,
+};
+
+// Flagging and taming samples that are far from the rest. NaNs are NonFinite, as they are for
+// the statistics these are built on, and the *_with versions take a NanPolicy instead.
+
+// How far from the middle a value has to be to count as an outlier. Each method works out a low
+// and a high fence, and anything strictly outside them is flagged.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutlierMethod {
+    // Tukey's fences: below Q1 - k * IQR or above Q3 + k * IQR. k = 1.5 is the usual choice,
+    // k = 3 only catches the "far out" ones. Doesn't assume any particular distribution.
+    // https://en.wikipedia.org/wiki/Outlier#Tukey's_fences
+    Iqr(f64),
+    // More than this many (population) standard deviations from the mean, usually 3. The
+    // outliers themselves inflate the standard deviation, so this misses them in small samples:
+    // with n values no z-score can be above (n - 1) / sqrt(n), which is under 3 for n <= 10.
+    ZScore(f64),
+    // A modified z-score of 0.6745 * |x - median| / MAD above this, usually 3.5. Like ZScore but
+    // built from medians, so the outliers can't hide themselves. When more than half the values
+    // are equal the MAD is 0, and anything different from the median is flagged.
+    // https://www.itl.nist.gov/div898/handbook/eda/section3/eda35h.htm
+    Mad(f64),
+}
+
+impl OutlierMethod {
+    fn threshold(self) -> f64 {
+        match self {
+            OutlierMethod::Iqr(t) | OutlierMethod::ZScore(t) | OutlierMethod::Mad(t) => t,
+        }
+    }
+}
+
+// The modified z-score scales the MAD by this so it matches the standard deviation on normal
+// data.
+const MAD_TO_STDDEV: f64 = 0.6745;
+
+// The median of each value's distance from the median. A spread that, unlike the standard
+// deviation, doesn't care how extreme the extremes are.
+// https://en.wikipedia.org/wiki/Median_absolute_deviation
+pub fn median_absolute_deviation<T: Numeric>(v: &[T]) -> Result<f64, StatsError> {
+    median_absolute_deviation_with(v, NanPolicy::Error)
+}
+
+pub fn median_absolute_deviation_with<T: Numeric>(
+    v: &[T],
+    nan: NanPolicy,
+) -> Result<f64, StatsError> {
+    let v = nan.filter(v)?;
+    let middle = median_with(&v, nan)?;
+    let deviations: Vec<f64> = v.iter().map(|x| (x.to_f64() - middle).abs()).collect();
+    median_with(&deviations, nan)
+}
+
+// The (low, high) fences `method` puts around `v`. Values strictly outside them are outliers.
+pub fn outlier_fences<T: Numeric>(
+    v: &[T],
+    method: OutlierMethod,
+) -> Result<(f64, f64), StatsError> {
+    outlier_fences_with(v, method, NanPolicy::Error)
+}
+
+// Under Propagate, a NaN makes both fences NaN.
+pub fn outlier_fences_with<T: Numeric>(
+    v: &[T],
+    method: OutlierMethod,
+    nan: NanPolicy,
+) -> Result<(f64, f64), StatsError> {
+    let threshold = method.threshold();
+    if !(threshold.is_finite() && threshold > 0.0) {
+        return Err(StatsError::InvalidThreshold(threshold));
+    }
+
+    let v = nan.filter(v)?;
+    match method {
+        OutlierMethod::Iqr(k) => {
+            let quartiles = quantiles_with(&v, &[0.25, 0.75], Interpolation::Linear, nan)?;
+            let (q1, q3) = (quartiles[0], quartiles[1]);
+            Ok((q1 - k * (q3 - q1), q3 + k * (q3 - q1)))
+        }
+        OutlierMethod::ZScore(z) => {
+            let (mean, stddev) = (mean_with(&v, nan)?, population_stddev_with(&v, nan)?);
+            Ok((mean - z * stddev, mean + z * stddev))
+        }
+        OutlierMethod::Mad(z) => {
+            let middle = median_with(&v, nan)?;
+            let distance = z * median_absolute_deviation_with(&v, nan)? / MAD_TO_STDDEV;
+            Ok((middle - distance, middle + distance))
+        }
+    }
+}
+
+// The index and value of every outlier in `v`, in their original order.
+pub fn outliers<T: Numeric>(v: &[T], method: OutlierMethod) -> Result<Vec<(usize, T)>, StatsError> {
+    outliers_with(v, method, NanPolicy::Error)
+}
+
+// The indices are still into `v` under Skip, and the NaNs are never outliers. Under Propagate the
+// fences are NaN, and nothing is outside them.
+pub fn outliers_with<T: Numeric>(
+    v: &[T],
+    method: OutlierMethod,
+    nan: NanPolicy,
+) -> Result<Vec<(usize, T)>, StatsError> {
+    let (low, high) = outlier_fences_with(v, method, nan)?;
+    Ok(v.iter()
+        .enumerate()
+        .filter(|(_, x)| x.to_f64() < low || x.to_f64() > high)
+        .map(|(i, &x)| (i, x))
+        .collect())
+}
+
+// How many values `proportion` of `v` takes off each end, after checking it's in [0, 0.5), which
+// always leaves at least one value in the middle.
+fn trim_count<T: Numeric>(v: &[T], proportion: f64) -> Result<usize, StatsError> {
+    if !(0.0..0.5).contains(&proportion) {
+        return Err(StatsError::InvalidProportion(proportion));
+    }
+    check_not_empty(v)?;
+    Ok((proportion * v.len() as f64) as usize)
+}
+
+fn sorted<T: Numeric>(v: &[T]) -> Vec<T> {
+    let mut sorted = v.to_vec();
+    sorted.sort_unstable_by(|a, b| a.total_cmp(b));
+    sorted
+}
+
+// The mean after dropping the smallest and largest `proportion` of the values (rounded down to a
+// whole number of values), e.g. 0.1 drops the bottom and top 10%. 0 is the plain mean, and
+// proportions close to 0.5 approach the median.
+// https://en.wikipedia.org/wiki/Truncated_mean
+pub fn trimmed_mean<T: Numeric>(v: &[T], proportion: f64) -> Result<f64, StatsError> {
+    trimmed_mean_with(v, proportion, NanPolicy::Error)
+}
+
+pub fn trimmed_mean_with<T: Numeric>(
+    v: &[T],
+    proportion: f64,
+    nan: NanPolicy,
+) -> Result<f64, StatsError> {
+    let v = nan.filter(v)?;
+    let k = trim_count(&v, proportion)?;
+    if let Some(nan) = first_nan(&v) {
+        return Ok(nan.to_f64());
+    }
+
+    let sorted = sorted(&v);
+    mean_with(&sorted[k..sorted.len() - k], nan)
+}
+
+// A copy of `v`, in the same order, with the values that trimmed_mean would drop clamped to the
+// smallest and largest values it would keep, so the outliers stay counted but can't pull too far.
+// https://en.wikipedia.org/wiki/Winsorizing
+pub fn winsorize<T: Numeric>(v: &[T], proportion: f64) -> Result<Vec<T>, StatsError> {
+    winsorize_with(v, proportion, NanPolicy::Error)
+}
+
+// Under Skip the NaNs are left out of the copy, and under Propagate every value in it is NaN.
+pub fn winsorize_with<T: Numeric>(
+    v: &[T],
+    proportion: f64,
+    nan: NanPolicy,
+) -> Result<Vec<T>, StatsError> {
+    let v = nan.filter(v)?;
+    let k = trim_count(&v, proportion)?;
+    if let Some(nan) = first_nan(&v) {
+        return Ok(vec![nan; v.len()]);
+    }
+
+    let sorted = sorted(&v);
+    let (low, high) = (sorted[k], sorted[sorted.len() - 1 - k]);
+
+    Ok(v.iter()
+        .map(|&x| {
+            if x.total_cmp(&low).is_lt() {
+                low
+            } else if x.total_cmp(&high).is_gt() {
+                high
+            } else {
+                x
+            }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Response times in ms with two slow requests.
+    const V: [i32; 12] = [12, 14, 11, 13, 250, 15, 12, 14, 13, 11, 90, 12];
+
+    #[test]
+    fn iqr_flags_values_outside_tukeys_fences() {
+        // Q1 = 12, Q3 = 14.25, so the fences are 8.625 and 17.625.
+        assert_eq!(
+            outlier_fences(&V, OutlierMethod::Iqr(1.5)),
+            Ok((8.625, 17.625))
+        );
+        assert_eq!(
+            outliers(&V, OutlierMethod::Iqr(1.5)),
+            Ok(vec![(4, 250), (10, 90)])
+        );
+    }
+
+    #[test]
+    fn z_score_is_fooled_by_the_outliers_it_looks_for() {
+        // 250 drags the mean and stddev up so far that 90 looks normal.
+        assert_eq!(outliers(&V, OutlierMethod::ZScore(2.0)), Ok(vec![(4, 250)]));
+        assert_eq!(outliers(&V, OutlierMethod::ZScore(3.0)), Ok(vec![(4, 250)]));
+    }
+
+    #[test]
+    fn mad_works() {
+        assert_eq!(median_absolute_deviation(&V), Ok(1.0));
+        assert_eq!(median_absolute_deviation(&[1, 2, 3, 4, 100]), Ok(1.0));
+        assert_eq!(
+            outliers(&V, OutlierMethod::Mad(3.5)),
+            Ok(vec![(4, 250), (10, 90)])
+        );
+    }
+
+    #[test]
+    fn mad_of_zero_flags_anything_off_the_median() {
+        let v = [5.0, 5.0, 5.0, 5.1, 4.0];
+
+        assert_eq!(
+            outliers(&v, OutlierMethod::Mad(3.5)),
+            Ok(vec![(3, 5.1), (4, 4.0)])
+        );
+        assert_eq!(outliers(&[7, 7, 7], OutlierMethod::ZScore(3.0)), Ok(vec![]));
+    }
+
+    #[test]
+    fn bad_input_is_an_error() {
+        assert_eq!(
+            outliers(&V, OutlierMethod::Iqr(0.0)),
+            Err(StatsError::InvalidThreshold(0.0))
+        );
+        assert!(outliers(&V, OutlierMethod::Mad(f64::NAN)).is_err());
+        assert_eq!(
+            outliers::<i32>(&[], OutlierMethod::ZScore(3.0)),
+            Err(StatsError::Empty)
+        );
+        assert_eq!(
+            outliers(&[1.0, f64::NAN], OutlierMethod::Mad(3.5)),
+            Err(StatsError::NonFinite)
+        );
+        assert_eq!(
+            trimmed_mean(&V, 0.5),
+            Err(StatsError::InvalidProportion(0.5))
+        );
+        assert_eq!(
+            winsorize(&V, -0.1),
+            Err(StatsError::InvalidProportion(-0.1))
+        );
+        assert_eq!(trimmed_mean::<i32>(&[], 0.1), Err(StatsError::Empty));
+    }
+
+    #[test]
+    fn trimmed_mean_drops_both_ends() {
+        let v = [1, 2, 3, 4, 5, 6, 7, 8, 9, 1000];
+
+        assert_eq!(trimmed_mean(&v, 0.0), crate::maths::mean(&v));
+        // 10% of 10 is one value off each end.
+        assert_eq!(trimmed_mean(&v, 0.1), Ok(5.5));
+        // 15% rounds down to one value too.
+        assert_eq!(trimmed_mean(&v, 0.15), Ok(5.5));
+        assert_eq!(trimmed_mean(&v, 0.49), Ok(5.5));
+        assert_eq!(trimmed_mean(&[3], 0.4), Ok(3.0));
+    }
+
+    #[test]
+    fn winsorize_clamps_in_place_order() {
+        let v = [1000, 2, 3, 4, 5, 6, 7, 8, 9, -50];
+
+        assert_eq!(winsorize(&v, 0.1), Ok(vec![9, 2, 3, 4, 5, 6, 7, 8, 9, 2]));
+        assert_eq!(winsorize(&v, 0.0), Ok(v.to_vec()));
+        assert_eq!(
+            winsorize(&[0.5, -1.0, 2.0, 0.0], 0.25),
+            Ok(vec![0.5, 0.0, 0.5, 0.0])
+        );
+    }
+
+    #[test]
+    fn nan_policies() {
+        let mut v: Vec<f64> = V.iter().map(|&x| f64::from(x)).collect();
+        v.insert(3, f64::NAN);
+        let clean: Vec<f64> = v.iter().copied().filter(|x| !x.is_nan()).collect();
+        let method = OutlierMethod::Iqr(1.5);
+
+        assert_eq!(median_absolute_deviation_with(&v, NanPolicy::Skip), Ok(1.0));
+        assert_eq!(
+            outlier_fences_with(&v, method, NanPolicy::Skip),
+            Ok((8.625, 17.625))
+        );
+        // The indices are into v, NaN and all.
+        assert_eq!(
+            outliers_with(&v, method, NanPolicy::Skip),
+            Ok(vec![(5, 250.0), (11, 90.0)])
+        );
+        assert_eq!(
+            trimmed_mean_with(&v, 0.1, NanPolicy::Skip),
+            trimmed_mean(&clean, 0.1)
+        );
+        assert_eq!(
+            winsorize_with(&v, 0.1, NanPolicy::Skip),
+            winsorize(&clean, 0.1)
+        );
+
+        assert!(median_absolute_deviation_with(&v, NanPolicy::Propagate)
+            .unwrap()
+            .is_nan());
+        for method in [
+            OutlierMethod::Iqr(1.5),
+            OutlierMethod::ZScore(3.0),
+            OutlierMethod::Mad(3.5),
+        ] {
+            let (low, high) = outlier_fences_with(&v, method, NanPolicy::Propagate).unwrap();
+            assert!(low.is_nan() && high.is_nan(), "{method:?}");
+            assert_eq!(outliers_with(&v, method, NanPolicy::Propagate), Ok(vec![]));
+        }
+        assert!(trimmed_mean_with(&v, 0.1, NanPolicy::Propagate)
+            .unwrap()
+            .is_nan());
+        let winsorized = winsorize_with(&v, 0.1, NanPolicy::Propagate).unwrap();
+        assert_eq!(winsorized.len(), v.len());
+        assert!(winsorized.iter().all(|x| x.is_nan()));
+
+        assert_eq!(
+            trimmed_mean_with(&v, 0.1, NanPolicy::Error),
+            Err(StatsError::NonFinite)
+        );
+        assert_eq!(
+            winsorize_with(&[f64::NAN], 0.1, NanPolicy::Skip),
+            Err(StatsError::Empty)
+        );
+    }
+}
@@ -0,0 +1,266 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::panic;
+use std::thread;
+
+use super::error::check_not_empty;
+use super::histogram::bin_edges;
+use super::summary::summarize;
+use super::{
+    count_values, Binning, Histogram, Modes, NanPolicy, Numeric, RunningStats, StatsError, Summary,
+};
+
+// Versions of mode_n, describe and Histogram::new that split the slice into one chunk per thread,
+// compute a partial result for each chunk on its own scoped thread, and merge them. Scoped
+// threads (unlike the thread::spawn in the threads binary) can borrow the slice, so nothing gets
+// copied or moved into them.
+// https://doc.rust-lang.org/std/thread/fn.scope.html
+//
+// `threads` is how many chunks to split into, but each chunk gets at least MIN_CHUNK_LEN values,
+// so a big thread count on a small slice can't spawn a thread per value. available_threads() is
+// a good default. Only worth it for big slices: spawning a thread costs tens of microseconds.
+const MIN_CHUNK_LEN: usize = 1000;
+
+// The number of threads the machine can run at once, or 1 if that can't be found out.
+pub fn available_threads() -> usize {
+    thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+// How many chunks map_chunks splits `len` values into: `threads` of them, as long as each gets
+// MIN_CHUNK_LEN values, and always at least one.
+fn chunk_count(len: usize, threads: usize) -> usize {
+    threads.min(len / MIN_CHUNK_LEN).max(1)
+}
+
+// Runs `f` on each of chunk_count(v.len(), threads) roughly equal chunks of `v` in parallel, and
+// returns the results in chunk order. A panic in `f` is re-raised here.
+fn map_chunks<'a, T, R, F>(v: &'a [T], threads: usize, f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&'a [T]) -> R + Sync,
+{
+    let chunk_len = v.len().div_ceil(chunk_count(v.len(), threads)).max(1);
+    let f = &f;
+    thread::scope(|scope| {
+        let handles: Vec<_> = v
+            .chunks(chunk_len)
+            .map(|chunk| scope.spawn(move || f(chunk)))
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap_or_else(|e| panic::resume_unwind(e)))
+            .collect()
+    })
+}
+
+// Same result as mode_n. Each thread counts its chunk, and the counts are added up.
+pub fn par_mode<T: Hash + Ord + Clone + Sync>(
+    v: &[T],
+    threads: usize,
+) -> Result<Modes<T>, StatsError> {
+    let mut value_counts: HashMap<&T, usize> = HashMap::new();
+    for partial in map_chunks(v, threads, count_values) {
+        for (x, count) in partial {
+            *value_counts.entry(x).or_insert(0) += count;
+        }
+    }
+
+    Modes::from_counts(value_counts)
+}
+
+// Same result as describe, up to rounding: the chunks' RunningStats are merged instead of every
+// value being pushed into one.
+pub fn par_describe<T>(v: &[T], threads: usize) -> Result<Summary<T>, StatsError>
+where
+    T: Numeric + Send + Sync,
+    T::Wide: Send,
+{
+    par_describe_with(v, threads, NanPolicy::Error)
+}
+
+// Same result as describe_with, up to rounding.
+pub fn par_describe_with<T>(
+    v: &[T],
+    threads: usize,
+    nan: NanPolicy,
+) -> Result<Summary<T>, StatsError>
+where
+    T: Numeric + Send + Sync,
+    T::Wide: Send,
+{
+    let v = nan.filter(v)?;
+    check_not_empty(&v)?;
+
+    let partials = map_chunks(&v, threads, |chunk| {
+        let mut stats = RunningStats::with_nan_policy(nan);
+        let mut sum = T::Wide::default();
+        for &x in chunk {
+            stats.push(x);
+            sum = sum + x.widen();
+        }
+        (stats, sum)
+    });
+
+    let mut stats = RunningStats::with_nan_policy(nan);
+    let mut sum = T::Wide::default();
+    for (partial_stats, partial_sum) in partials {
+        stats.merge(&partial_stats);
+        sum = sum + partial_sum;
+    }
+
+    summarize(&stats, sum, &v, nan)
+}
+
+// Same result as Histogram::new. The edges are placed first (on this thread, since the automatic
+// binnings need the min and max, or the quartiles, of everything), then each thread counts its
+// chunk into the same bins.
+pub fn par_histogram<T: Numeric + Sync>(
+    v: &[T],
+    binning: Binning,
+    threads: usize,
+) -> Result<Histogram, StatsError> {
+    par_histogram_with(v, binning, threads, NanPolicy::Error)
+}
+
+// Same result as Histogram::with_nan_policy.
+pub fn par_histogram_with<T: Numeric + Sync>(
+    v: &[T],
+    binning: Binning,
+    threads: usize,
+    nan: NanPolicy,
+) -> Result<Histogram, StatsError> {
+    let v = nan.filter(v)?;
+    check_not_empty(&v)?;
+
+    let empty = Histogram::empty(bin_edges(&v, binning)?);
+    let partials = map_chunks(&v, threads, |chunk| {
+        let mut partial = empty.clone();
+        partial.count(chunk);
+        partial
+    });
+
+    let mut histogram = empty;
+    for partial in &partials {
+        histogram.merge(partial);
+    }
+    Ok(histogram)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maths;
+
+    fn samples(len: usize) -> Vec<i32> {
+        let mut rng = maths::Rng::new(1);
+        (0..len).map(|_| rng.below(1000) as i32 - 500).collect()
+    }
+
+    #[test]
+    fn par_mode_matches_mode_n() {
+        let v = samples(10_000);
+
+        for threads in [1, 2, 3, 8, 20_000] {
+            assert_eq!(
+                par_mode(&v, threads),
+                maths::mode_n(&v),
+                "{threads} threads"
+            );
+        }
+    }
+
+    #[test]
+    fn par_describe_matches_describe() {
+        let v = samples(10_000);
+        let expected = maths::describe(&v).unwrap();
+
+        for threads in [1, 4, 7] {
+            let summary = par_describe(&v, threads).unwrap();
+            assert_eq!(summary.count, expected.count);
+            assert_eq!(summary.sum, expected.sum);
+            assert_eq!((summary.min, summary.max), (expected.min, expected.max));
+            assert!((summary.mean - expected.mean).abs() < 1e-9);
+            assert!((summary.population_variance - expected.population_variance).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn par_histogram_matches_histogram() {
+        let v = samples(10_000);
+
+        for binning in [
+            Binning::Sturges,
+            Binning::FreedmanDiaconis,
+            Binning::FixedWidth(33.0),
+            Binning::Edges(vec![-100.0, 0.0, 100.0]),
+        ] {
+            assert_eq!(
+                par_histogram(&v, binning.clone(), 4),
+                Histogram::new(&v, binning)
+            );
+        }
+    }
+
+    #[test]
+    fn nan_policies_match_the_sequential_versions() {
+        let mut v: Vec<f64> = samples(5_000).into_iter().map(f64::from).collect();
+        v[1234] = f64::NAN;
+        v[4321] = f64::NAN;
+
+        for nan in [NanPolicy::Skip, NanPolicy::Error] {
+            let (expected, summary) =
+                (maths::describe_with(&v, nan), par_describe_with(&v, 3, nan));
+            assert_eq!(
+                summary.map(|s| (s.count, s.sum, s.min, s.max)),
+                expected.map(|s| (s.count, s.sum, s.min, s.max)),
+                "{nan:?}"
+            );
+        }
+        let propagated = par_describe_with(&v, 3, NanPolicy::Propagate).unwrap();
+        assert_eq!(propagated.count, v.len());
+        assert!(propagated.mean.is_nan() && propagated.min.is_nan());
+
+        for nan in [NanPolicy::Skip, NanPolicy::Propagate, NanPolicy::Error] {
+            assert_eq!(
+                par_histogram_with(&v, Binning::Sturges, 3, nan),
+                Histogram::with_nan_policy(&v, Binning::Sturges, nan),
+                "{nan:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn errors_match_the_sequential_versions() {
+        assert_eq!(par_mode::<i32>(&[], 4), Err(StatsError::Empty));
+        assert_eq!(par_describe::<i32>(&[], 4), Err(StatsError::Empty));
+        assert_eq!(
+            par_describe(&[1.0, f64::NAN], 2),
+            Err(StatsError::NonFinite)
+        );
+        assert_eq!(
+            par_histogram(&[1, 2], Binning::FixedWidth(-1.0), 2),
+            Err(StatsError::InvalidBinning)
+        );
+    }
+
+    #[test]
+    fn chunks_are_capped_by_length() {
+        assert_eq!(chunk_count(10_000, 8), 8);
+        assert_eq!(chunk_count(10_000, 20_000), 10);
+        assert_eq!(chunk_count(999, 4), 1);
+        assert_eq!(chunk_count(0, 4), 1);
+
+        let v = samples(10_000);
+        let lens = map_chunks(&v, 20_000, |chunk| chunk.len());
+        assert_eq!(lens, vec![1000; 10]);
+    }
+
+    #[test]
+    fn zero_threads_means_one() {
+        let v = samples(100);
+
+        assert_eq!(par_mode(&v, 0), maths::mode_n(&v));
+        assert!(available_threads() >= 1);
+    }
+}
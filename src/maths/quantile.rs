@@ -0,0 +1,406 @@
+use super::error::{check_no_nan, check_not_empty};
+use super::nan::first_nan;
+use super::{NanPolicy, Numeric, StatsError};
+
+// How to pick a value when the quantile falls between two data points. With the data sorted and
+// h = (n - 1) * q, the methods (named after numpy's) look at v[floor(h)] and v[ceil(h)]:
+// https://numpy.org/doc/stable/reference/generated/numpy.quantile.html
+// NearestRank is the textbook percentile that's always one of the data points:
+// https://en.wikipedia.org/wiki/Percentile#The_nearest-rank_method
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interpolation {
+    // v[ceil(q * n) - 1], or the first value for q = 0.
+    NearestRank,
+    // Straight line between v[floor(h)] and v[ceil(h)]. numpy's and Excel's default.
+    Linear,
+    // v[floor(h)]
+    Lower,
+    // v[ceil(h)]
+    Higher,
+    // Average of v[floor(h)] and v[ceil(h)].
+    Midpoint,
+}
+
+impl Interpolation {
+    // The 0-based positions in the sorted data this method reads (the same one twice if it only
+    // needs one), and how far between them the answer is.
+    pub(crate) fn positions(self, len: usize, q: f64) -> (usize, usize, f64) {
+        let h = snap_to_integer((len - 1) as f64 * q);
+        // Past 2^53, (len - 1) as f64 can round up to len, so h can land one past the end.
+        let last = len - 1;
+        let (floor, ceil) = (
+            (h.floor() as usize).min(last),
+            (h.ceil() as usize).min(last),
+        );
+
+        match self {
+            Interpolation::NearestRank => {
+                let rank = (snap_to_integer(q * len as f64).ceil() as usize).clamp(1, len);
+                (rank - 1, rank - 1, 0.0)
+            }
+            Interpolation::Linear => (floor, ceil, h - h.floor()),
+            Interpolation::Lower => (floor, floor, 0.0),
+            Interpolation::Higher => (ceil, ceil, 0.0),
+            Interpolation::Midpoint => (floor, ceil, 0.5),
+        }
+    }
+
+    // Infinities are fine as order statistics, but there's no value between -inf and inf, so
+    // interpolating across them is NonFinite rather than a NaN. Between two equal values (even
+    // two infinities) the answer is just that value, and next to a single infinity it's that
+    // infinity, whichever end it's at.
+    pub(crate) fn combine(
+        self,
+        lower_val: f64,
+        upper_val: f64,
+        fraction: f64,
+    ) -> Result<f64, StatsError> {
+        let combined = match self {
+            _ if lower_val == upper_val => lower_val,
+            Interpolation::Midpoint => midpoint(lower_val, upper_val),
+            Interpolation::Linear if fraction > 0.0 => {
+                match (lower_val.is_infinite(), upper_val.is_infinite()) {
+                    (true, false) => lower_val,
+                    (false, true) => upper_val,
+                    _ => lower_val + fraction * (upper_val - lower_val),
+                }
+            }
+            _ => lower_val,
+        };
+
+        if combined.is_nan() {
+            Err(StatsError::NonFinite)
+        } else {
+            Ok(combined)
+        }
+    }
+}
+
+// The average of two values, for every median and Midpoint quantile in the crate. Halving first
+// means f64::MAX and friends can't overflow to infinity. Halving is exact for anything converted
+// from an integer, so this only costs precision on subnormal floats.
+fn midpoint(lower_val: f64, upper_val: f64) -> f64 {
+    lower_val / 2.0 + upper_val / 2.0
+}
+
+// Positions like q * len are meant to be whole numbers when they come that close, but q isn't
+// exact in binary, e.g. 0.28 * 25.0 is 7.000000000000001. Left alone, ceil would then pick the
+// next position over. Anything within a few ulps of a whole number is taken to be it.
+fn snap_to_integer(x: f64) -> f64 {
+    let nearest = x.round();
+    if (x - nearest).abs() <= 4.0 * f64::EPSILON * nearest.abs() {
+        nearest
+    } else {
+        x
+    }
+}
+
+pub(crate) fn check_quantile(q: f64) -> Result<(), StatsError> {
+    if (0.0..=1.0).contains(&q) {
+        Ok(())
+    } else {
+        Err(StatsError::InvalidQuantile(q))
+    }
+}
+
+// The q-th quantile (0 <= q <= 1) of `v`. Fails on an empty slice, a NaN in `v`, or a q outside
+// [0, 1].
+pub fn quantile<T: Numeric>(v: &[T], q: f64, method: Interpolation) -> Result<f64, StatsError> {
+    quantile_with(v, q, method, NanPolicy::Error)
+}
+
+// quantile with a choice of what to do about NaNs. Under Propagate a NaN anywhere makes the
+// quantile NaN.
+pub fn quantile_with<T: Numeric>(
+    v: &[T],
+    q: f64,
+    method: Interpolation,
+    nan: NanPolicy,
+) -> Result<f64, StatsError> {
+    let v = nan.filter(v)?;
+    check_not_empty(&v)?;
+    check_quantile(q)?;
+    if let Some(nan) = first_nan(&v) {
+        return Ok(nan.to_f64());
+    }
+
+    quantile_in_place(&mut v.into_owned(), q, method)
+}
+
+// Same as quantile, but works on the caller's slice instead of a copy. The slice is left
+// partially reordered.
+pub fn quantile_in_place<T: Numeric>(
+    v: &mut [T],
+    q: f64,
+    method: Interpolation,
+) -> Result<f64, StatsError> {
+    check_not_empty(v)?;
+    check_quantile(q)?;
+    check_no_nan(v)?;
+
+    let (lower_index, upper_index, fraction) = method.positions(v.len(), q);
+
+    // Sorting the whole thing is O(n lg n) just to read one or two values. Selection only puts
+    // the value at lower_index in place, with everything smaller to its left and everything bigger
+    // to its right. The std implementation is an introselect: quickselect that falls back to
+    // median of medians when pivots keep going badly, so it's O(n) even in the worst case.
+    // https://doc.rust-lang.org/std/primitive.slice.html#method.select_nth_unstable_by
+    let (_, &mut lower_val, higher) = v.select_nth_unstable_by(lower_index, T::total_cmp);
+
+    // upper_index is at most lower_index + 1, i.e. the smallest value right of the selected one.
+    // When it's different, it's a valid index, so `higher` is never empty there.
+    let upper_val = if upper_index == lower_index {
+        lower_val
+    } else {
+        higher
+            .iter()
+            .copied()
+            .min_by(T::total_cmp)
+            .unwrap_or(lower_val)
+    };
+
+    method.combine(lower_val.to_f64(), upper_val.to_f64(), fraction)
+}
+
+// Several quantiles of the same data, e.g. p50/p90/p99 latency. Sorts one copy once instead of
+// selecting for each q. Fails the same ways quantile does, if any q is bad.
+pub fn quantiles<T: Numeric>(
+    v: &[T],
+    qs: &[f64],
+    method: Interpolation,
+) -> Result<Vec<f64>, StatsError> {
+    quantiles_with(v, qs, method, NanPolicy::Error)
+}
+
+pub fn quantiles_with<T: Numeric>(
+    v: &[T],
+    qs: &[f64],
+    method: Interpolation,
+    nan: NanPolicy,
+) -> Result<Vec<f64>, StatsError> {
+    let v = nan.filter(v)?;
+    check_not_empty(&v)?;
+    for &q in qs {
+        check_quantile(q)?;
+    }
+    if let Some(nan) = first_nan(&v) {
+        return Ok(vec![nan.to_f64(); qs.len()]);
+    }
+
+    let mut sorted = v.into_owned();
+    sorted.sort_by(T::total_cmp);
+
+    qs.iter()
+        .map(|&q| {
+            let (lower_index, upper_index, fraction) = method.positions(sorted.len(), q);
+            let lower_val = sorted[lower_index].to_f64();
+            let upper_val = sorted[upper_index].to_f64();
+            method.combine(lower_val, upper_val, fraction)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use Interpolation::*;
+
+    // From the nearest-rank example on Wikipedia's Percentile page.
+    const WIKIPEDIA: [i32; 5] = [15, 20, 35, 40, 50];
+
+    #[test]
+    fn nearest_rank_matches_reference_values() {
+        let qs = [0.05, 0.3, 0.4, 0.5, 1.0];
+
+        assert_eq!(
+            quantiles(&WIKIPEDIA, &qs, NearestRank).unwrap(),
+            vec![15.0, 20.0, 20.0, 35.0, 50.0]
+        );
+    }
+
+    #[test]
+    fn rounding_error_does_not_move_the_rank() {
+        // 0.28 * 25 is 7.000000000000001 and 0.14 * 50 is 7.000000000000001 in f64, but both
+        // are rank 7.
+        let v25: Vec<i32> = (1..=25).collect();
+        let v50: Vec<i32> = (1..=50).collect();
+        assert_eq!(quantile(&v25, 0.28, NearestRank), Ok(7.0));
+        assert_eq!(quantile(&v50, 0.14, NearestRank), Ok(7.0));
+        assert_eq!(quantiles(&v25, &[0.28], NearestRank), Ok(vec![7.0]));
+
+        // Every q = rank / n should give back exactly that rank.
+        for n in 1..=200 {
+            let v: Vec<i32> = (1..=n).collect();
+            for rank in 1..=n {
+                let q = rank as f64 / n as f64;
+                assert_eq!(
+                    quantile(&v, q, NearestRank),
+                    Ok(rank as f64),
+                    "{rank} / {n}"
+                );
+            }
+        }
+
+        // The same goes for h = (n - 1) * q in the other methods.
+        let v26: Vec<i32> = (0..=25).collect();
+        for method in [Lower, Higher, Midpoint, Linear] {
+            assert_eq!(quantile(&v26, 0.28, method), Ok(7.0), "{method:?}");
+        }
+    }
+
+    #[test]
+    fn linear_matches_reference_values() {
+        // numpy.quantile([15, 20, 35, 40, 50], [0.4, 0.9]) == [29., 46.]
+        assert_eq!(quantile(&WIKIPEDIA, 0.4, Linear).unwrap(), 29.0);
+        assert_eq!(quantile(&WIKIPEDIA, 0.9, Linear).unwrap(), 46.0);
+    }
+
+    #[test]
+    fn every_method_matches_numpy() {
+        // numpy.quantile([1, 2, 3, 4], 0.4, method=...)
+        let v = [4, 1, 3, 2];
+
+        assert!((quantile(&v, 0.4, Linear).unwrap() - 2.2).abs() < 1e-12);
+        assert_eq!(quantile(&v, 0.4, Lower).unwrap(), 2.0);
+        assert_eq!(quantile(&v, 0.4, Higher).unwrap(), 3.0);
+        assert_eq!(quantile(&v, 0.4, Midpoint).unwrap(), 2.5);
+        assert_eq!(quantile(&v, 0.4, NearestRank).unwrap(), 2.0);
+    }
+
+    #[test]
+    fn latency_percentiles() {
+        let latencies: Vec<u32> = (1..=100).collect();
+        let linear = quantiles(&latencies, &[0.9, 0.95, 0.99], Linear).unwrap();
+
+        assert!((linear[0] - 90.1).abs() < 1e-9);
+        assert!((linear[1] - 95.05).abs() < 1e-9);
+        assert!((linear[2] - 99.01).abs() < 1e-9);
+        assert_eq!(
+            quantiles(&latencies, &[0.9, 0.95, 0.99], NearestRank).unwrap(),
+            vec![90.0, 95.0, 99.0]
+        );
+    }
+
+    #[test]
+    fn extremes_are_min_and_max() {
+        for method in [NearestRank, Linear, Lower, Higher, Midpoint] {
+            assert_eq!(quantile(&WIKIPEDIA, 0.0, method).unwrap(), 15.0);
+            assert_eq!(quantile(&WIKIPEDIA, 1.0, method).unwrap(), 50.0);
+        }
+    }
+
+    #[test]
+    fn quantile_and_quantiles_agree() {
+        let v = [9, -3, 7, 7, 0, 12, 5, 1];
+        let qs = [0.0, 0.1, 0.25, 0.5, 0.75, 0.9, 1.0];
+
+        for method in [NearestRank, Linear, Lower, Higher, Midpoint] {
+            let batch = quantiles(&v, &qs, method).unwrap();
+            for (&q, &expected) in qs.iter().zip(&batch) {
+                assert_eq!(quantile(&v, q, method).unwrap(), expected, "{method:?} {q}");
+            }
+        }
+    }
+
+    #[test]
+    fn invalid_quantiles_are_errors() {
+        assert_eq!(
+            quantile(&WIKIPEDIA, -0.1, Linear),
+            Err(StatsError::InvalidQuantile(-0.1))
+        );
+        assert_eq!(
+            quantile(&WIKIPEDIA, 1.1, Linear),
+            Err(StatsError::InvalidQuantile(1.1))
+        );
+        assert!(matches!(
+            quantile(&WIKIPEDIA, f64::NAN, Linear),
+            Err(StatsError::InvalidQuantile(q)) if q.is_nan()
+        ));
+        assert_eq!(
+            quantiles(&WIKIPEDIA, &[0.5, 2.0], Linear),
+            Err(StatsError::InvalidQuantile(2.0))
+        );
+    }
+
+    #[test]
+    fn nan_input_is_an_error() {
+        assert_eq!(
+            quantile(&[1.0, f64::NAN], 0.5, Linear),
+            Err(StatsError::NonFinite)
+        );
+        assert_eq!(
+            quantiles(&[1.0, f64::NAN], &[0.5], Linear),
+            Err(StatsError::NonFinite)
+        );
+    }
+
+    #[test]
+    fn nan_policy_is_honoured() {
+        let v = [3.0, f64::NAN, 1.0, 2.0];
+
+        assert!(quantile_with(&v, 0.5, Linear, NanPolicy::Propagate)
+            .unwrap()
+            .is_nan());
+        assert_eq!(quantile_with(&v, 0.5, Linear, NanPolicy::Skip), Ok(2.0));
+        assert_eq!(
+            quantiles_with(&v, &[0.0, 1.0], Linear, NanPolicy::Skip),
+            Ok(vec![1.0, 3.0])
+        );
+        assert_eq!(
+            quantile_with(&v, 0.5, Linear, NanPolicy::Error),
+            Err(StatsError::NonFinite)
+        );
+        assert_eq!(
+            quantile_with(&[f64::NAN], 0.5, Linear, NanPolicy::Skip),
+            Err(StatsError::Empty)
+        );
+    }
+
+    #[test]
+    fn infinities_are_ordered() {
+        let v = [f64::NEG_INFINITY, 1.0, f64::INFINITY];
+
+        assert_eq!(quantile(&v, 0.0, Lower).unwrap(), f64::NEG_INFINITY);
+        assert_eq!(quantile(&v, 0.5, Lower).unwrap(), 1.0);
+    }
+
+    #[test]
+    fn interpolating_between_infinities_is_an_error() {
+        let (inf, neg_inf) = (f64::INFINITY, f64::NEG_INFINITY);
+
+        assert_eq!(
+            quantile(&[neg_inf, inf], 0.5, Midpoint),
+            Err(StatsError::NonFinite)
+        );
+        assert_eq!(
+            quantile(&[neg_inf, inf], 0.3, Linear),
+            Err(StatsError::NonFinite)
+        );
+        assert_eq!(
+            quantiles(&[neg_inf, inf], &[0.0, 0.5], Linear),
+            Err(StatsError::NonFinite)
+        );
+        // Between equal infinities, or next to one, there's a proper answer.
+        assert_eq!(quantile(&[inf, inf], 0.3, Linear), Ok(inf));
+        assert_eq!(quantile(&[inf, inf], 0.5, Midpoint), Ok(inf));
+        assert_eq!(quantile(&[1.0, inf], 0.5, Linear), Ok(inf));
+        assert_eq!(quantile(&[neg_inf, 1.0], 0.5, Linear), Ok(neg_inf));
+        assert_eq!(quantile(&[neg_inf, 1.0], 0.3, Linear), Ok(neg_inf));
+        assert_eq!(quantile(&[neg_inf, 1.0], 0.5, Midpoint), Ok(neg_inf));
+        assert_eq!(quantile(&[1.0, inf], 0.5, Midpoint), Ok(inf));
+        assert_eq!(
+            quantiles(&[neg_inf, inf], &[0.0, 1.0], Linear),
+            Ok(vec![neg_inf, inf])
+        );
+    }
+
+    #[test]
+    fn empty_input_is_an_error() {
+        assert_eq!(quantile::<i32>(&[], 0.5, Linear), Err(StatsError::Empty));
+        assert_eq!(
+            quantiles::<i32>(&[], &[0.5], Linear),
+            Err(StatsError::Empty)
+        );
+    }
+}
@@ -0,0 +1,98 @@
+// A small seedable pseudo-random number generator, so resampling doesn't need a dependency and a
+// given seed always gives the same numbers, on every platform. Not for anything security
+// related.
+//
+// It's xoshiro256**, with its state filled in from the seed by SplitMix64, as its authors
+// recommend: https://prng.di.unimi.it/
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: [u64; 4],
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        // SplitMix64 turns any seed, even 0, into a well mixed, non-zero state.
+        let mut seed = seed;
+        let mut split_mix = || {
+            seed = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+            let mut z = seed;
+            z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+            z ^ (z >> 31)
+        };
+
+        Rng {
+            state: [split_mix(), split_mix(), split_mix(), split_mix()],
+        }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let [s0, s1, s2, s3] = &mut self.state;
+        let result = s1.wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = *s1 << 17;
+
+        *s2 ^= *s0;
+        *s3 ^= *s1;
+        *s1 ^= *s2;
+        *s0 ^= *s3;
+        *s2 ^= t;
+        *s3 = s3.rotate_left(45);
+
+        result
+    }
+
+    // A fraction in [0, 1), from the top 53 bits so every f64 it can return is equally likely.
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    // A whole number in [0, n), by scaling a u64 down rather than taking a remainder. The bias
+    // is at most n / 2^64, far too small to show up in any statistic. Panics if `n` is 0.
+    pub fn below(&mut self, n: usize) -> usize {
+        assert!(n != 0, "n must be non-zero");
+        ((self.next_u64() as u128 * n as u128) >> 64) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_same_numbers() {
+        let mut a = Rng::new(42);
+        let mut b = Rng::new(42);
+        let mut c = Rng::new(43);
+
+        let from_a: Vec<u64> = (0..100).map(|_| a.next_u64()).collect();
+        let from_b: Vec<u64> = (0..100).map(|_| b.next_u64()).collect();
+        let from_c: Vec<u64> = (0..100).map(|_| c.next_u64()).collect();
+        assert_eq!(from_a, from_b);
+        assert_ne!(from_a, from_c);
+    }
+
+    #[test]
+    fn below_is_in_range_and_roughly_uniform() {
+        let mut rng = Rng::new(0);
+        let mut counts = [0; 10];
+        for _ in 0..100_000 {
+            counts[rng.below(10)] += 1;
+        }
+
+        // Each count is about 10,000 with a standard deviation of about 95.
+        for count in counts {
+            assert!((9_500..10_500).contains(&count), "{counts:?}");
+        }
+        assert_eq!(rng.below(1), 0);
+    }
+
+    #[test]
+    fn next_f64_is_a_fraction() {
+        let mut rng = Rng::new(7);
+        let fractions: Vec<f64> = (0..10_000).map(|_| rng.next_f64()).collect();
+
+        assert!(fractions.iter().all(|x| (0.0..1.0).contains(x)));
+        let mean = fractions.iter().sum::<f64>() / fractions.len() as f64;
+        assert!((mean - 0.5).abs() < 0.01);
+    }
+}
@@ -0,0 +1,420 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap, VecDeque};
+
+use super::numeric::TotalOrd;
+use super::{Interpolation, NanPolicy, Numeric, StatsError};
+
+// The same midpoint median takes (see quantile::midpoint), and NonFinite between opposite
+// infinities.
+fn average(lower_val: f64, upper_val: f64) -> Result<f64, StatsError> {
+    Interpolation::Midpoint.combine(lower_val, upper_val, 0.5)
+}
+
+// The median of everything pushed so far, without keeping the values sorted. The smaller half
+// lives in a max-heap and the bigger half in a min-heap, with the lower half allowed to be one
+// bigger, so the median is always on top of one or both heaps. Each push is O(lg n).
+// https://en.wikipedia.org/wiki/Median#Running_median
+//
+// NaNs aren't kept or counted. Under Skip they're ignored, and once one has been pushed the
+// median is NonFinite under Error and that NaN under Propagate, like median_with.
+#[derive(Debug, Clone)]
+pub struct RunningMedian<T> {
+    lower: BinaryHeap<TotalOrd<T>>,
+    upper: BinaryHeap<Reverse<TotalOrd<T>>>,
+    nan_policy: NanPolicy,
+    // The first NaN pushed, unless the policy is Skip.
+    nan: Option<T>,
+}
+
+impl<T: Numeric> RunningMedian<T> {
+    // NaNs are NonFinite errors, like median.
+    pub fn new() -> RunningMedian<T> {
+        RunningMedian::with_nan_policy(NanPolicy::Error)
+    }
+
+    pub fn with_nan_policy(nan: NanPolicy) -> RunningMedian<T> {
+        RunningMedian {
+            lower: BinaryHeap::new(),
+            upper: BinaryHeap::new(),
+            nan_policy: nan,
+            nan: None,
+        }
+    }
+
+    pub fn push(&mut self, x: T) {
+        if x.is_nan() {
+            if self.nan_policy != NanPolicy::Skip && self.nan.is_none() {
+                self.nan = Some(x);
+            }
+            return;
+        }
+
+        match self.lower.peek() {
+            Some(&TotalOrd(lower_max)) if x.total_cmp(&lower_max).is_gt() => {
+                self.upper.push(Reverse(TotalOrd(x)))
+            }
+            _ => self.lower.push(TotalOrd(x)),
+        }
+
+        if self.lower.len() > self.upper.len() + 1 {
+            if let Some(moved) = self.lower.pop() {
+                self.upper.push(Reverse(moved));
+            }
+        } else if self.upper.len() > self.lower.len() {
+            if let Some(Reverse(moved)) = self.upper.pop() {
+                self.lower.push(moved);
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.lower.len() + self.upper.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lower.is_empty()
+    }
+
+    // Same answer as maths::median_with over everything pushed so far.
+    pub fn median(&self) -> Result<f64, StatsError> {
+        match (self.nan, self.nan_policy) {
+            (Some(_), NanPolicy::Error) => return Err(StatsError::NonFinite),
+            (Some(nan), _) => return Ok(nan.to_f64()),
+            (None, _) => {}
+        }
+
+        let &TotalOrd(lower_max) = self.lower.peek().ok_or(StatsError::Empty)?;
+        match self.upper.peek() {
+            Some(&Reverse(TotalOrd(upper_min))) if self.lower.len() == self.upper.len() => {
+                average(lower_max.to_f64(), upper_min.to_f64())
+            }
+            _ => Ok(lower_max.to_f64()),
+        }
+    }
+}
+
+impl<T: Numeric> Default for RunningMedian<T> {
+    fn default() -> Self {
+        RunningMedian::new()
+    }
+}
+
+impl<T: Numeric> Extend<T> for RunningMedian<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for x in iter {
+            self.push(x);
+        }
+    }
+}
+
+// A sorted multiset. BinaryHeap can't remove an arbitrary value, which SlidingMedian needs to
+// evict the oldest one, so its halves are counted BTreeMaps instead.
+#[derive(Debug, Clone)]
+struct Multiset<T> {
+    counts: BTreeMap<TotalOrd<T>, usize>,
+    len: usize,
+}
+
+impl<T: Numeric> Multiset<T> {
+    fn new() -> Multiset<T> {
+        Multiset {
+            counts: BTreeMap::new(),
+            len: 0,
+        }
+    }
+
+    fn insert(&mut self, x: T) {
+        *self.counts.entry(TotalOrd(x)).or_insert(0) += 1;
+        self.len += 1;
+    }
+
+    fn remove(&mut self, x: T) -> bool {
+        let Some(count) = self.counts.get_mut(&TotalOrd(x)) else {
+            return false;
+        };
+        *count -= 1;
+        if *count == 0 {
+            self.counts.remove(&TotalOrd(x));
+        }
+        self.len -= 1;
+        true
+    }
+
+    fn first(&self) -> Option<T> {
+        self.counts.first_key_value().map(|(&TotalOrd(x), _)| x)
+    }
+
+    fn last(&self) -> Option<T> {
+        self.counts.last_key_value().map(|(&TotalOrd(x), _)| x)
+    }
+}
+
+// The median of the last `window` values pushed, for smoothing noisy metrics. Same two-halves
+// idea as RunningMedian, but the oldest value is evicted once the window is full. Each push is
+// O(lg window).
+//
+// Under Skip, NaNs are ignored and don't take up a place in the window. Otherwise a NaN does,
+// and until it's evicted the median is NonFinite (Error) or that NaN (Propagate).
+#[derive(Debug, Clone)]
+pub struct SlidingMedian<T> {
+    window: usize,
+    values: VecDeque<T>,
+    lower: Multiset<T>,
+    upper: Multiset<T>,
+    nan_policy: NanPolicy,
+    // How many of `values` are NaNs, which aren't in either half.
+    nans: usize,
+}
+
+impl<T: Numeric> SlidingMedian<T> {
+    // Panics if `window` is 0, the same way slice::windows does. NaNs are NonFinite errors, like
+    // median.
+    pub fn new(window: usize) -> SlidingMedian<T> {
+        SlidingMedian::with_nan_policy(window, NanPolicy::Error)
+    }
+
+    pub fn with_nan_policy(window: usize, nan: NanPolicy) -> SlidingMedian<T> {
+        assert!(window != 0, "window size must be non-zero");
+
+        SlidingMedian {
+            window,
+            values: VecDeque::with_capacity(window),
+            lower: Multiset::new(),
+            upper: Multiset::new(),
+            nan_policy: nan,
+            nans: 0,
+        }
+    }
+
+    // Adds `x`, and returns the value it pushed out of the window, if the window was full.
+    pub fn push(&mut self, x: T) -> Option<T> {
+        if x.is_nan() && self.nan_policy == NanPolicy::Skip {
+            return None;
+        }
+
+        let evicted = if self.values.len() == self.window {
+            self.evict()
+        } else {
+            None
+        };
+
+        self.values.push_back(x);
+        if x.is_nan() {
+            self.nans += 1;
+            return evicted;
+        }
+        match self.lower.last() {
+            Some(lower_max) if x.total_cmp(&lower_max).is_gt() => self.upper.insert(x),
+            _ => self.lower.insert(x),
+        }
+        self.rebalance();
+
+        evicted
+    }
+
+    // Removes the oldest value from the window.
+    pub fn evict(&mut self) -> Option<T> {
+        let oldest = self.values.pop_front()?;
+        if oldest.is_nan() {
+            self.nans -= 1;
+            return Some(oldest);
+        }
+        // Everything in lower is <= everything in upper, so if it isn't in one it's in the other.
+        if !self.upper.remove(oldest) {
+            self.lower.remove(oldest);
+        }
+        self.rebalance();
+
+        Some(oldest)
+    }
+
+    fn rebalance(&mut self) {
+        if self.lower.len > self.upper.len + 1 {
+            if let Some(moved) = self.lower.last() {
+                self.lower.remove(moved);
+                self.upper.insert(moved);
+            }
+        } else if self.upper.len > self.lower.len {
+            if let Some(moved) = self.upper.first() {
+                self.upper.remove(moved);
+                self.lower.insert(moved);
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    // Same answer as maths::median_with over the current window.
+    pub fn median(&self) -> Result<f64, StatsError> {
+        if self.nans > 0 {
+            if self.nan_policy == NanPolicy::Error {
+                return Err(StatsError::NonFinite);
+            }
+            if let Some(nan) = self.values.iter().find(|x| x.is_nan()) {
+                return Ok(nan.to_f64());
+            }
+        }
+
+        let lower_max = self.lower.last().ok_or(StatsError::Empty)?;
+        match self.upper.first() {
+            Some(upper_min) if self.lower.len == self.upper.len => {
+                average(lower_max.to_f64(), upper_min.to_f64())
+            }
+            _ => Ok(lower_max.to_f64()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maths;
+
+    // Deterministic pseudo-random values with plenty of duplicates.
+    fn samples(len: usize) -> Vec<i32> {
+        let mut rng = maths::Rng::new(12345);
+        (0..len).map(|_| rng.below(50) as i32 - 25).collect()
+    }
+
+    #[test]
+    fn running_median_matches_median_of_every_prefix() {
+        let v = samples(300);
+        let mut running = RunningMedian::new();
+
+        for i in 0..v.len() {
+            running.push(v[i]);
+            assert_eq!(running.len(), i + 1);
+            assert_eq!(running.median(), maths::median(&v[..=i]), "{:?}", &v[..=i]);
+        }
+    }
+
+    #[test]
+    fn running_median_works_with_floats() {
+        let mut running = RunningMedian::new();
+        running.extend([2.5, -1.0, 10.0, 3.0]);
+
+        assert_eq!(running.median().unwrap(), 2.75);
+    }
+
+    #[test]
+    fn running_median_is_empty_error_when_empty() {
+        let running: RunningMedian<i32> = RunningMedian::new();
+
+        assert!(running.is_empty());
+        assert_eq!(running.median(), Err(StatsError::Empty));
+    }
+
+    #[test]
+    fn running_median_follows_its_nan_policy() {
+        let v = [1.0, f64::NAN, 4.0, 2.0];
+        for nan in [NanPolicy::Error, NanPolicy::Skip, NanPolicy::Propagate] {
+            let mut running = RunningMedian::with_nan_policy(nan);
+            running.extend(v);
+
+            let expected = maths::median_with(&v, nan);
+            match running.median() {
+                Ok(m) if m.is_nan() => assert!(expected.unwrap().is_nan()),
+                m => assert_eq!(m, expected, "{nan:?}"),
+            }
+            assert_eq!(running.len(), 3);
+        }
+    }
+
+    #[test]
+    fn running_median_between_opposite_infinities_is_an_error() {
+        let mut running = RunningMedian::new();
+        running.extend([f64::NEG_INFINITY, f64::INFINITY]);
+
+        assert_eq!(running.median(), Err(StatsError::NonFinite));
+    }
+
+    #[test]
+    fn sliding_median_matches_median_of_every_window() {
+        let v = samples(300);
+        for window in [1, 2, 3, 10, 25] {
+            let mut sliding = SlidingMedian::new(window);
+            for i in 0..v.len() {
+                let evicted = sliding.push(v[i]);
+                let start = (i + 1).saturating_sub(window);
+
+                assert_eq!(evicted, (i >= window).then(|| v[i - window]));
+                assert_eq!(sliding.len(), i + 1 - start);
+                assert_eq!(sliding.median(), maths::median(&v[start..=i]));
+            }
+        }
+    }
+
+    #[test]
+    fn sliding_median_evicts_down_to_empty() {
+        let mut sliding = SlidingMedian::new(3);
+        sliding.push(1);
+        sliding.push(5);
+        sliding.push(3);
+
+        assert_eq!(sliding.evict(), Some(1));
+        assert_eq!(sliding.median(), Ok(4.0));
+        assert_eq!(sliding.evict(), Some(5));
+        assert_eq!(sliding.median(), Ok(3.0));
+        assert_eq!(sliding.evict(), Some(3));
+        assert_eq!(sliding.median(), Err(StatsError::Empty));
+        assert_eq!(sliding.evict(), None);
+        assert!(sliding.is_empty());
+    }
+
+    #[test]
+    fn sliding_median_smooths_a_spike() {
+        let mut sliding = SlidingMedian::new(3);
+        let smoothed: Vec<f64> = [10, 11, 500, 12, 11]
+            .into_iter()
+            .map(|x| {
+                sliding.push(x);
+                sliding.median().unwrap()
+            })
+            .collect();
+
+        assert_eq!(smoothed, vec![10.0, 10.5, 11.0, 12.0, 12.0]);
+    }
+
+    #[test]
+    fn sliding_median_nan_lasts_until_evicted() {
+        let mut sliding = SlidingMedian::new(2);
+        sliding.push(1.0);
+        sliding.push(f64::NAN);
+        assert_eq!(sliding.median(), Err(StatsError::NonFinite));
+        sliding.push(3.0);
+        assert_eq!(sliding.median(), Err(StatsError::NonFinite));
+        sliding.push(5.0);
+        assert_eq!(sliding.median(), Ok(4.0));
+
+        let mut sliding = SlidingMedian::with_nan_policy(2, NanPolicy::Propagate);
+        sliding.push(1.0);
+        sliding.push(f64::NAN);
+        assert!(sliding.median().unwrap().is_nan());
+        assert_eq!(sliding.evict(), Some(1.0));
+        assert!(sliding.evict().unwrap().is_nan());
+        assert_eq!(sliding.median(), Err(StatsError::Empty));
+    }
+
+    #[test]
+    fn sliding_median_can_skip_nan() {
+        let mut sliding = SlidingMedian::with_nan_policy(2, NanPolicy::Skip);
+        for x in [1.0, 3.0, f64::NAN] {
+            sliding.push(x);
+        }
+
+        assert_eq!(sliding.len(), 2);
+        assert_eq!(sliding.median(), Ok(2.0));
+    }
+
+    #[test]
+    #[should_panic(expected = "window size must be non-zero")]
+    fn sliding_median_rejects_empty_window() {
+        SlidingMedian::<i32>::new(0);
+    }
+}
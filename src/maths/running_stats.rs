@@ -0,0 +1,352 @@
+use super::{NanPolicy, Numeric, StatsError};
+
+// Count, mean, variance, min and max of a stream of values, in constant space. The mean and
+// variance are updated one value at a time with Welford's algorithm. The textbook
+// sum(x^2) - sum(x)^2 / n formula subtracts two huge, nearly equal numbers and can lose every
+// significant digit (or go negative); this doesn't.
+// https://en.wikipedia.org/wiki/Algorithms_for_calculating_variance#Welford's_online_algorithm
+//
+// The batch functions (mean, population_variance, describe, ...) are built on this, so pushing
+// the same values gives exactly the same answers. The exception is their mean of integers, which
+// is the exact sum over the count and can be a rounding step closer than the running mean.
+//
+// NaNs are handled the way the *_with functions handle them, by a NanPolicy chosen up front. A
+// NaN is never counted: under Skip it's ignored, under Error every statistic is NonFinite from
+// then on, and under Propagate every statistic is that NaN.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RunningStats<T> {
+    count: usize,
+    mean: f64,
+    // Sum of squared differences from the current mean.
+    m2: f64,
+    min: Option<T>,
+    max: Option<T>,
+    nan_policy: NanPolicy,
+    // The first NaN pushed, unless the policy is Skip.
+    nan: Option<T>,
+}
+
+impl<T: Numeric> RunningStats<T> {
+    // NaNs are NonFinite errors, like the plain batch functions.
+    pub fn new() -> RunningStats<T> {
+        RunningStats::with_nan_policy(NanPolicy::Error)
+    }
+
+    pub fn with_nan_policy(nan: NanPolicy) -> RunningStats<T> {
+        RunningStats {
+            count: 0,
+            mean: 0.0,
+            m2: 0.0,
+            min: None,
+            max: None,
+            nan_policy: nan,
+            nan: None,
+        }
+    }
+
+    // True if `x` is a NaN, which has been dealt with.
+    fn take_nan(&mut self, x: T) -> bool {
+        if !x.is_nan() {
+            return false;
+        }
+        if self.nan_policy != NanPolicy::Skip && self.nan.is_none() {
+            self.nan = Some(x);
+        }
+        true
+    }
+
+    pub fn push(&mut self, x: T) {
+        if self.take_nan(x) {
+            return;
+        }
+
+        self.count += 1;
+        let f = x.to_f64();
+        let delta = f - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (f - self.mean);
+
+        self.min = Some(match self.min {
+            Some(min) if min.total_cmp(&x).is_le() => min,
+            _ => x,
+        });
+        self.max = Some(match self.max {
+            Some(max) if max.total_cmp(&x).is_ge() => max,
+            _ => x,
+        });
+    }
+
+    // Same as pushing `x` `count` times, in O(1). For data that comes as (value, count) pairs.
+    pub fn push_n(&mut self, x: T, count: usize) {
+        if count == 0 || self.take_nan(x) {
+            return;
+        }
+
+        self.merge(&RunningStats {
+            count,
+            mean: x.to_f64(),
+            m2: 0.0,
+            min: Some(x),
+            max: Some(x),
+            nan_policy: self.nan_policy,
+            nan: None,
+        });
+    }
+
+    // Folds in stats gathered separately, e.g. on another thread, as if every value pushed there
+    // had been pushed here. Uses Chan et al.'s pairwise update of the mean and m2. A NaN that
+    // `other` kept counts as pushed here, under this one's policy.
+    // https://en.wikipedia.org/wiki/Algorithms_for_calculating_variance#Parallel_algorithm
+    pub fn merge(&mut self, other: &RunningStats<T>) {
+        if let Some(nan) = other.nan {
+            self.take_nan(nan);
+        }
+        if other.count == 0 {
+            return;
+        }
+        if self.count == 0 {
+            self.count = other.count;
+            self.mean = other.mean;
+            self.m2 = other.m2;
+            self.min = other.min;
+            self.max = other.max;
+            return;
+        }
+
+        let count = self.count + other.count;
+        let delta = other.mean - self.mean;
+        let (n_self, n_other) = (self.count as f64, other.count as f64);
+        self.mean += delta * n_other / count as f64;
+        self.m2 += other.m2 + delta * delta * n_self * n_other / count as f64;
+        self.count = count;
+
+        // Both sides are non-empty, so both have a min and a max.
+        if let (Some(min), Some(other_min)) = (self.min, other.min) {
+            if other_min.total_cmp(&min).is_lt() {
+                self.min = Some(other_min);
+            }
+        }
+        if let (Some(max), Some(other_max)) = (self.max, other.max) {
+            if other_max.total_cmp(&max).is_gt() {
+                self.max = Some(other_max);
+            }
+        }
+    }
+
+    // How many values have been pushed, not counting NaNs.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    // Everything below is Empty until something has been pushed, and NonFinite (Error) or NaN
+    // (Propagate) once a NaN has.
+
+    // Ok(None) when there's no NaN to report and the stats aren't empty.
+    fn check(&self, needed: usize) -> Result<Option<T>, StatsError> {
+        match (self.nan, self.nan_policy) {
+            (Some(_), NanPolicy::Error) => Err(StatsError::NonFinite),
+            (Some(nan), _) => Ok(Some(nan)),
+            (None, _) if self.count == 0 => Err(StatsError::Empty),
+            (None, _) if self.count < needed => Err(StatsError::NotEnoughValues {
+                needed,
+                got: self.count,
+            }),
+            (None, _) => Ok(None),
+        }
+    }
+
+    // Checks the stats, and returns `f()` or the NaN.
+    fn statistic(&self, needed: usize, f: impl FnOnce() -> f64) -> Result<f64, StatsError> {
+        Ok(match self.check(needed)? {
+            Some(nan) => nan.to_f64(),
+            None => f(),
+        })
+    }
+
+    pub fn mean(&self) -> Result<f64, StatsError> {
+        self.statistic(1, || self.mean)
+    }
+
+    // Divides by n.
+    pub fn population_variance(&self) -> Result<f64, StatsError> {
+        self.statistic(1, || self.m2 / self.count as f64)
+    }
+
+    // Divides by n - 1 (Bessel's correction). NotEnoughValues for fewer than two values.
+    pub fn sample_variance(&self) -> Result<f64, StatsError> {
+        self.statistic(2, || self.m2 / (self.count - 1) as f64)
+    }
+
+    pub fn population_stddev(&self) -> Result<f64, StatsError> {
+        Ok(self.population_variance()?.sqrt())
+    }
+
+    pub fn sample_stddev(&self) -> Result<f64, StatsError> {
+        Ok(self.sample_variance()?.sqrt())
+    }
+
+    pub fn min(&self) -> Result<T, StatsError> {
+        self.check(1)?.or(self.min).ok_or(StatsError::Empty)
+    }
+
+    pub fn max(&self) -> Result<T, StatsError> {
+        self.check(1)?.or(self.max).ok_or(StatsError::Empty)
+    }
+}
+
+impl<T: Numeric> Default for RunningStats<T> {
+    fn default() -> Self {
+        RunningStats::new()
+    }
+}
+
+impl<T: Numeric> Extend<T> for RunningStats<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for x in iter {
+            self.push(x);
+        }
+    }
+}
+
+impl<T: Numeric> FromIterator<T> for RunningStats<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut stats = RunningStats::new();
+        stats.extend(iter);
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maths;
+
+    const V: [i32; 8] = [2, 4, 4, 4, 5, 5, 7, 9];
+
+    fn assert_close(actual: Result<f64, StatsError>, expected: Result<f64, StatsError>) {
+        match (actual, expected) {
+            (Ok(a), Ok(e)) => assert!((a - e).abs() < 1e-9, "expected {e}, got {a}"),
+            _ => assert_eq!(actual, expected),
+        }
+    }
+
+    #[test]
+    fn agrees_with_batch_functions() {
+        let stats: RunningStats<i32> = V.iter().copied().collect();
+
+        assert_eq!(stats.count(), V.len());
+        assert_eq!(stats.mean(), maths::mean(&V));
+        assert_eq!(stats.population_variance(), maths::population_variance(&V));
+        assert_eq!(stats.sample_variance(), maths::sample_variance(&V));
+        assert_eq!(stats.population_stddev(), Ok(2.0));
+        assert_eq!(stats.min(), maths::min(&V));
+        assert_eq!(stats.max(), maths::max(&V));
+    }
+
+    #[test]
+    fn empty_stats_are_empty_errors() {
+        let stats: RunningStats<f64> = RunningStats::new();
+
+        assert_eq!(stats.count(), 0);
+        assert_eq!(stats.mean(), Err(StatsError::Empty));
+        assert_eq!(stats.population_variance(), Err(StatsError::Empty));
+        assert_eq!(stats.sample_variance(), Err(StatsError::Empty));
+        assert_eq!(stats.min(), Err(StatsError::Empty));
+        assert_eq!(stats.max(), Err(StatsError::Empty));
+    }
+
+    #[test]
+    fn one_value_is_not_enough_for_sample_variance() {
+        let stats: RunningStats<i32> = [3].into_iter().collect();
+
+        assert_eq!(stats.population_variance(), Ok(0.0));
+        assert_eq!(
+            stats.sample_variance(),
+            Err(StatsError::NotEnoughValues { needed: 2, got: 1 })
+        );
+    }
+
+    #[test]
+    fn nan_is_an_error_by_default() {
+        let stats: RunningStats<f64> = [1.0, f64::NAN, 3.0].into_iter().collect();
+
+        assert_eq!(stats.count(), 2);
+        assert_eq!(stats.mean(), Err(StatsError::NonFinite));
+        assert_eq!(stats.max(), Err(StatsError::NonFinite));
+    }
+
+    #[test]
+    fn nan_can_be_skipped_or_propagated() {
+        let v = [1.0, f64::NAN, 3.0];
+
+        let mut skipped = RunningStats::with_nan_policy(NanPolicy::Skip);
+        skipped.extend(v);
+        assert_eq!(skipped.mean(), maths::mean_with(&v, NanPolicy::Skip));
+        assert_eq!(skipped.max(), Ok(3.0));
+
+        let mut propagated = RunningStats::with_nan_policy(NanPolicy::Propagate);
+        propagated.extend(v);
+        assert!(propagated.mean().unwrap().is_nan());
+        assert!(propagated.sample_stddev().unwrap().is_nan());
+        assert!(propagated.min().unwrap().is_nan());
+    }
+
+    #[test]
+    fn merge_carries_over_a_nan() {
+        let mut left: RunningStats<f64> = [1.0, 2.0].into_iter().collect();
+        let right: RunningStats<f64> = [f64::NAN].into_iter().collect();
+        left.merge(&right);
+
+        assert_eq!(left.count(), 2);
+        assert_eq!(left.mean(), Err(StatsError::NonFinite));
+    }
+
+    #[test]
+    fn merge_matches_pushing_everything_into_one() {
+        let all: RunningStats<i32> = V.iter().copied().collect();
+
+        for split in 0..=V.len() {
+            let mut left: RunningStats<i32> = V[..split].iter().copied().collect();
+            let right: RunningStats<i32> = V[split..].iter().copied().collect();
+            left.merge(&right);
+
+            assert_eq!(left.count(), all.count());
+            assert_close(left.mean(), all.mean());
+            assert_close(left.population_variance(), all.population_variance());
+            assert_close(left.sample_variance(), all.sample_variance());
+            assert_eq!(left.min(), all.min());
+            assert_eq!(left.max(), all.max());
+        }
+    }
+
+    #[test]
+    fn push_n_matches_pushing_repeatedly() {
+        let mut repeated = RunningStats::new();
+        let mut pushed = RunningStats::new();
+        for (x, count) in [(2, 1), (4, 3), (5, 2), (7, 0), (9, 1)] {
+            repeated.push_n(x, count);
+            for _ in 0..count {
+                pushed.push(x);
+            }
+        }
+
+        assert_eq!(repeated.count(), pushed.count());
+        assert_close(repeated.mean(), pushed.mean());
+        assert_close(repeated.sample_variance(), pushed.sample_variance());
+        assert_eq!(repeated.max(), Ok(9));
+    }
+
+    #[test]
+    fn merge_many_chunks() {
+        let v: Vec<f64> = (0..1000).map(|i| (i as f64 * 0.37).sin() * 100.0).collect();
+        let mut merged = RunningStats::new();
+        for chunk in v.chunks(37) {
+            merged.merge(&chunk.iter().copied().collect());
+        }
+
+        assert_close(merged.mean(), maths::mean(&v));
+        assert_close(merged.sample_variance(), maths::sample_variance(&v));
+        assert_eq!(merged.min(), maths::min(&v));
+        assert_eq!(merged.max(), maths::max(&v));
+    }
+}
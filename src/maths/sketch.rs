@@ -0,0 +1,337 @@
+use super::quantile::check_quantile;
+use super::{NanPolicy, Numeric, StatsError};
+
+// Approximate quantiles in bounded memory, for data that doesn't fit in memory or arrives as a
+// stream. This is a KLL sketch (Karnin, Lang and Liberty, 2016), following Liberty's reference
+// implementation: https://github.com/edoliberty/streaming-quantiles
+//
+// Values go into level 0. When a level fills up it's sorted and every other value (starting at
+// a random offset) is promoted to the next level, where each value stands in for twice as many of
+// the original values. Lower levels get geometrically smaller capacities, so memory stays around
+// 3k values no matter how much is added, and a quantile is off by roughly 1.7/k in rank (about
+// 1% for the default k = 200).
+//
+// NaNs aren't added to the sketch. Under Skip they're ignored, and once one has been added every
+// quantile is NonFinite under Error and that NaN under Propagate, like quantile_with.
+#[derive(Debug, Clone)]
+pub struct KllSketch<T> {
+    k: usize,
+    // compactors[h] holds values that each stand for 2^h of the values added.
+    compactors: Vec<Vec<T>>,
+    // Total values held across all compactors, and how many we allow before compacting.
+    retained: usize,
+    max_retained: usize,
+    count: usize,
+    // xorshift64 state for the compaction coin flips. Seeded with a constant so results are
+    // reproducible.
+    rng_state: u64,
+    nan_policy: NanPolicy,
+    // The first NaN added, unless the policy is Skip.
+    nan: Option<T>,
+}
+
+// Each level below the top has 2/3 the capacity of the one above it.
+const CAPACITY_DECAY: f64 = 2.0 / 3.0;
+
+const DEFAULT_K: usize = 200;
+
+impl<T: Numeric> KllSketch<T> {
+    // `k` trades memory for accuracy: roughly 3k values are kept, and the rank error is
+    // roughly 1.7/k. Panics if `k` is less than 2. NaNs are NonFinite errors, like quantile.
+    pub fn new(k: usize) -> KllSketch<T> {
+        KllSketch::with_nan_policy(k, NanPolicy::Error)
+    }
+
+    pub fn with_nan_policy(k: usize, nan: NanPolicy) -> KllSketch<T> {
+        assert!(k >= 2, "k must be at least 2");
+
+        let mut sketch = KllSketch {
+            k,
+            compactors: vec![],
+            retained: 0,
+            max_retained: 0,
+            count: 0,
+            rng_state: 0x2545_F491_4F6C_DD1D,
+            nan_policy: nan,
+            nan: None,
+        };
+        sketch.grow();
+        sketch
+    }
+
+    fn capacity(&self, level: usize) -> usize {
+        let depth = self.compactors.len() - level - 1;
+        (CAPACITY_DECAY.powi(depth as i32) * self.k as f64).ceil() as usize + 1
+    }
+
+    fn grow(&mut self) {
+        self.compactors.push(vec![]);
+        self.max_retained = (0..self.compactors.len()).map(|h| self.capacity(h)).sum();
+    }
+
+    fn coin_flip(&mut self) -> bool {
+        self.rng_state ^= self.rng_state << 13;
+        self.rng_state ^= self.rng_state >> 7;
+        self.rng_state ^= self.rng_state << 17;
+        self.rng_state & 1 == 1
+    }
+
+    pub fn add(&mut self, x: T) {
+        if x.is_nan() {
+            if self.nan_policy != NanPolicy::Skip && self.nan.is_none() {
+                self.nan = Some(x);
+            }
+            return;
+        }
+
+        self.compactors[0].push(x);
+        self.retained += 1;
+        self.count += 1;
+        if self.retained >= self.max_retained {
+            self.compress();
+        }
+    }
+
+    // Compacts the lowest level that's over capacity (one is enough to get back under
+    // max_retained).
+    fn compress(&mut self) {
+        for level in 0..self.compactors.len() {
+            if self.compactors[level].len() < self.capacity(level) {
+                continue;
+            }
+            if level + 1 == self.compactors.len() {
+                self.grow();
+            }
+
+            let offset = usize::from(self.coin_flip());
+            let mut values = std::mem::take(&mut self.compactors[level]);
+            values.sort_by(T::total_cmp);
+
+            // An odd value out stays behind at this level.
+            if values.len() % 2 == 1 {
+                if let Some(last) = values.pop() {
+                    self.compactors[level].push(last);
+                }
+            }
+            let promoted = values.iter().skip(offset).step_by(2).copied();
+            self.compactors[level + 1].extend(promoted);
+
+            self.retained = self.compactors.iter().map(Vec::len).sum();
+            return;
+        }
+    }
+
+    // Folds in a sketch built separately, e.g. over another shard of the data. The result is as
+    // accurate as one sketch fed both streams. A NaN that `other` kept counts as added here, under
+    // this one's policy.
+    pub fn merge(&mut self, other: &KllSketch<T>) {
+        if let Some(nan) = other.nan {
+            self.add(nan);
+        }
+        while self.compactors.len() < other.compactors.len() {
+            self.grow();
+        }
+        for (level, values) in other.compactors.iter().enumerate() {
+            self.compactors[level].extend_from_slice(values);
+        }
+        self.count += other.count;
+        self.retained = self.compactors.iter().map(Vec::len).sum();
+
+        while self.retained >= self.max_retained {
+            self.compress();
+        }
+    }
+
+    // How many values have been added, not counting NaNs.
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    // How many values the sketch is actually holding on to.
+    pub fn retained(&self) -> usize {
+        self.retained
+    }
+
+    // An approximation of the q-th quantile (0 <= q <= 1): one of the added values whose rank is
+    // within about 1.7/k of q. Fails if nothing's been added or q is outside [0, 1].
+    pub fn quantile(&self, q: f64) -> Result<f64, StatsError> {
+        check_quantile(q)?;
+        match (self.nan, self.nan_policy) {
+            (Some(_), NanPolicy::Error) => return Err(StatsError::NonFinite),
+            (Some(nan), _) => return Ok(nan.to_f64()),
+            (None, _) => {}
+        }
+        if self.is_empty() {
+            return Err(StatsError::Empty);
+        }
+
+        let mut weighted: Vec<(T, usize)> = self
+            .compactors
+            .iter()
+            .enumerate()
+            .flat_map(|(level, values)| values.iter().map(move |&x| (x, 1 << level)))
+            .collect();
+        weighted.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let total: usize = weighted.iter().map(|&(_, weight)| weight).sum();
+        let target = q * total as f64;
+        let mut cumulative = 0;
+        for &(x, weight) in &weighted {
+            cumulative += weight;
+            if cumulative as f64 >= target {
+                return Ok(x.to_f64());
+            }
+        }
+
+        weighted
+            .last()
+            .map(|&(x, _)| x.to_f64())
+            .ok_or(StatsError::Empty)
+    }
+}
+
+impl<T: Numeric> Default for KllSketch<T> {
+    fn default() -> Self {
+        KllSketch::new(DEFAULT_K)
+    }
+}
+
+impl<T: Numeric> Extend<T> for KllSketch<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for x in iter {
+            self.add(x);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maths;
+
+    fn samples(len: usize, seed: u64) -> Vec<i32> {
+        let mut rng = maths::Rng::new(seed);
+        (0..len).map(|_| rng.below(1 << 24) as i32).collect()
+    }
+
+    // The fraction of `sorted` that's <= x.
+    fn rank(sorted: &[i32], x: f64) -> f64 {
+        sorted.partition_point(|&y| y as f64 <= x) as f64 / sorted.len() as f64
+    }
+
+    // 1.7/k plus some slack for a single unlucky run.
+    const MAX_RANK_ERROR: f64 = 2.5 / DEFAULT_K as f64;
+
+    #[test]
+    fn median_is_within_error_bound_of_exact_median() {
+        for seed in [1, 2, 3, 4, 5] {
+            let v = samples(100_000, seed);
+            let mut sketch = KllSketch::default();
+            sketch.extend(v.iter().copied());
+
+            let exact = maths::median(&v).unwrap();
+            let lower = sketch.quantile(0.5 - MAX_RANK_ERROR).unwrap();
+            let upper = sketch.quantile(0.5 + MAX_RANK_ERROR).unwrap();
+            assert!(lower <= exact && exact <= upper, "seed {seed}");
+
+            let mut sorted = v.clone();
+            sorted.sort();
+            let estimate = sketch.quantile(0.5).unwrap();
+            assert!((rank(&sorted, estimate) - 0.5).abs() < MAX_RANK_ERROR);
+        }
+    }
+
+    #[test]
+    fn other_quantiles_are_within_error_bound() {
+        let v = samples(50_000, 42);
+        let mut sketch = KllSketch::default();
+        sketch.extend(v.iter().copied());
+
+        let mut sorted = v.clone();
+        sorted.sort();
+        for q in [0.01, 0.1, 0.25, 0.75, 0.9, 0.99] {
+            let estimate = sketch.quantile(q).unwrap();
+            assert!(
+                (rank(&sorted, estimate) - q).abs() < MAX_RANK_ERROR,
+                "q = {q}"
+            );
+        }
+    }
+
+    #[test]
+    fn memory_stays_bounded() {
+        let mut sketch = KllSketch::new(100);
+        sketch.extend(samples(1_000_000, 7));
+
+        assert_eq!(sketch.len(), 1_000_000);
+        assert!(sketch.retained() < 400, "retained {}", sketch.retained());
+    }
+
+    #[test]
+    fn small_inputs_are_exact() {
+        let v = [5, 1, 4, 2, 3];
+        let mut sketch = KllSketch::default();
+        sketch.extend(v);
+
+        assert_eq!(sketch.quantile(0.0), Ok(1.0));
+        assert_eq!(sketch.quantile(0.5), Ok(3.0));
+        assert_eq!(sketch.quantile(1.0), Ok(5.0));
+    }
+
+    #[test]
+    fn merged_sketch_is_within_error_bound() {
+        let v = samples(200_000, 9);
+        let mut merged = KllSketch::default();
+        for shard in v.chunks(30_000) {
+            let mut sketch = KllSketch::default();
+            sketch.extend(shard.iter().copied());
+            merged.merge(&sketch);
+        }
+
+        let mut sorted = v.clone();
+        sorted.sort();
+        assert_eq!(merged.len(), v.len());
+        let estimate = merged.quantile(0.5).unwrap();
+        assert!((rank(&sorted, estimate) - 0.5).abs() < MAX_RANK_ERROR);
+    }
+
+    #[test]
+    fn empty_sketch_and_invalid_quantiles_are_errors() {
+        let mut sketch: KllSketch<f64> = KllSketch::default();
+        assert_eq!(sketch.quantile(0.5), Err(StatsError::Empty));
+
+        sketch.add(1.0);
+        assert_eq!(
+            sketch.quantile(-0.1),
+            Err(StatsError::InvalidQuantile(-0.1))
+        );
+        assert!(sketch.quantile(f64::NAN).is_err());
+    }
+
+    #[test]
+    fn nan_follows_the_nan_policy() {
+        let v = [3.0, f64::NAN, 1.0, 2.0];
+        let sketch = |nan| {
+            let mut sketch = KllSketch::with_nan_policy(DEFAULT_K, nan);
+            sketch.extend(v);
+            sketch
+        };
+
+        assert_eq!(
+            sketch(NanPolicy::Error).quantile(0.5),
+            Err(StatsError::NonFinite)
+        );
+        assert_eq!(sketch(NanPolicy::Skip).quantile(0.5), Ok(2.0));
+        assert_eq!(sketch(NanPolicy::Skip).len(), 3);
+        assert!(sketch(NanPolicy::Propagate).quantile(0.5).unwrap().is_nan());
+
+        // The NaN survives a merge.
+        let mut merged = KllSketch::default();
+        merged.merge(&sketch(NanPolicy::Error));
+        assert_eq!(merged.quantile(0.5), Err(StatsError::NonFinite));
+    }
+}
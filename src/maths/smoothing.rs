@@ -0,0 +1,380 @@
+use super::Numeric;
+
+// Smoothing for time-ordered samples. Each function borrows the slice and returns a lazy
+// iterator, so the results can be chained into other iterator adapters or collected, and
+// nothing is computed until it's asked for.
+//
+// The windowed averages only yield once the window is full: a window of w over n values yields
+// n - w + 1 averages (none if n < w), the first one covering v[..w]. That way every value is an
+// average of the same number of samples, and zipping it with v[w - 1..] lines each average up
+// with its newest sample.
+//
+// There's no error to report from an iterator, so NaNs just propagate: any window with a NaN in
+// it averages to NaN.
+
+// The mean of each window of `window` consecutive values. Panics if `window` is 0.
+pub fn moving_average<T: Numeric>(v: &[T], window: usize) -> MovingAverage<'_, T> {
+    assert!(window != 0, "window must be non-zero");
+
+    MovingAverage {
+        v,
+        window,
+        end: 0,
+        sum: T::Wide::default(),
+        float_sum: CompensatedSum::default(),
+    }
+}
+
+// Keeps a running sum: add the newest value, subtract the one that fell out, so each step is
+// O(1). For integers the sum is in T::Wide, which is exact, so there's no drift. For floats (and
+// the 128-bit integers) a plain running sum would drift: after [1e17, 1.0] the 1.0 has been
+// rounded away, subtracting 1e17 leaves 0, and the error sticks around for the rest of the
+// series. So the float sum is compensated (see CompensatedSum), and re-summed from scratch every
+// `window` steps, and whenever it isn't finite, so what error there is can't build up and an
+// infinity or NaN is forgotten once it leaves the window.
+#[derive(Debug, Clone)]
+pub struct MovingAverage<'a, T: Numeric> {
+    v: &'a [T],
+    window: usize,
+    // Where the last window yielded ends (exclusive), or 0 before the first.
+    end: usize,
+    // Sum of the last window yielded, when T::EXACT_WIDE.
+    sum: T::Wide,
+    // Sum of the last window yielded, otherwise.
+    float_sum: CompensatedSum,
+}
+
+impl<T: Numeric> Iterator for MovingAverage<'_, T> {
+    type Item = f64;
+
+    fn next(&mut self) -> Option<f64> {
+        let end = (self.end + 1).max(self.window);
+        let window = self.v.get(end - self.window..end)?;
+
+        let sum = if T::EXACT_WIDE {
+            self.sum = if self.end == 0 {
+                window
+                    .iter()
+                    .fold(T::Wide::default(), |sum, &x| sum + x.widen())
+            } else {
+                self.sum + self.v[end - 1].widen() - self.v[end - 1 - self.window].widen()
+            };
+            T::wide_to_f64(self.sum)
+        } else {
+            let resum = self.end == 0
+                || end.is_multiple_of(self.window)
+                || !self.float_sum.value().is_finite();
+            if resum {
+                self.float_sum = CompensatedSum::default();
+                window.iter().for_each(|x| self.float_sum.add(x.to_f64()));
+            } else {
+                self.float_sum.add(self.v[end - 1].to_f64());
+                self.float_sum.add(-self.v[end - 1 - self.window].to_f64());
+            }
+            self.float_sum.value()
+        };
+        self.end = end;
+
+        Some(sum / self.window as f64)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self
+            .v
+            .len()
+            .saturating_sub((self.end + 1).max(self.window) - 1);
+        (remaining, Some(remaining))
+    }
+}
+
+impl<T: Numeric> ExactSizeIterator for MovingAverage<'_, T> {}
+
+// Neumaier's improvement on Kahan summation: alongside the sum, keep track of the low-order bits
+// each addition rounds off, and add them back at the end. The result is as good as summing in
+// twice the precision, so big and small values can share a window.
+// https://en.wikipedia.org/wiki/Kahan_summation_algorithm#Further_enhancements
+#[derive(Debug, Clone, Copy, Default)]
+struct CompensatedSum {
+    sum: f64,
+    compensation: f64,
+}
+
+impl CompensatedSum {
+    fn add(&mut self, x: f64) {
+        let total = self.sum + x;
+        self.compensation += if self.sum.abs() >= x.abs() {
+            (self.sum - total) + x
+        } else {
+            (x - total) + self.sum
+        };
+        self.sum = total;
+    }
+
+    fn value(&self) -> f64 {
+        // An infinity or NaN turns the compensation into NaN, and the plain sum is already right.
+        if self.sum.is_finite() {
+            self.sum + self.compensation
+        } else {
+            self.sum
+        }
+    }
+}
+
+// The weighted mean of each window, where the window is as long as `weights` and weights[i]
+// applies to the i-th oldest value in it. E.g. [1.0, 2.0, 3.0] is the usual linearly weighted
+// moving average, which counts the newest sample three times as much as the oldest. Panics if
+// `weights` is empty or sums to 0.
+pub fn weighted_moving_average<'a, T: Numeric>(
+    v: &'a [T],
+    weights: &'a [f64],
+) -> WeightedMovingAverage<'a, T> {
+    let total: f64 = weights.iter().sum();
+    assert!(!weights.is_empty(), "weights must be non-empty");
+    assert!(total != 0.0, "weights must not sum to 0");
+
+    WeightedMovingAverage {
+        windows: v.windows(weights.len()),
+        weights,
+        total,
+    }
+}
+
+// Each window is summed from scratch, so a step is O(window).
+#[derive(Debug, Clone)]
+pub struct WeightedMovingAverage<'a, T> {
+    windows: std::slice::Windows<'a, T>,
+    weights: &'a [f64],
+    total: f64,
+}
+
+impl<T: Numeric> Iterator for WeightedMovingAverage<'_, T> {
+    type Item = f64;
+
+    fn next(&mut self) -> Option<f64> {
+        let window = self.windows.next()?;
+        let weighted: f64 = window
+            .iter()
+            .zip(self.weights)
+            .map(|(&x, &weight)| x.to_f64() * weight)
+            .sum();
+        Some(weighted / self.total)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.windows.size_hint()
+    }
+}
+
+impl<T: Numeric> ExactSizeIterator for WeightedMovingAverage<'_, T> {}
+
+// Each value is alpha * the newest sample + (1 - alpha) * the previous value, so older samples
+// fade out geometrically instead of dropping off a window edge. Bigger alphas react faster.
+// There's no window to fill, so this yields one value per sample, starting from v[0] itself.
+// Panics unless 0 < alpha <= 1.
+// https://en.wikipedia.org/wiki/Exponential_smoothing
+pub fn exponential_moving_average<T: Numeric>(
+    v: &[T],
+    alpha: f64,
+) -> ExponentialMovingAverage<'_, T> {
+    assert!(alpha > 0.0 && alpha <= 1.0, "alpha must be in (0, 1]");
+
+    ExponentialMovingAverage {
+        values: v.iter(),
+        alpha,
+        average: None,
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ExponentialMovingAverage<'a, T> {
+    values: std::slice::Iter<'a, T>,
+    alpha: f64,
+    average: Option<f64>,
+}
+
+impl<T: Numeric> Iterator for ExponentialMovingAverage<'_, T> {
+    type Item = f64;
+
+    fn next(&mut self) -> Option<f64> {
+        let x = self.values.next()?.to_f64();
+        let average = match self.average {
+            Some(average) => average + self.alpha * (x - average),
+            None => x,
+        };
+        self.average = Some(average);
+        Some(average)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.values.size_hint()
+    }
+}
+
+impl<T: Numeric> ExactSizeIterator for ExponentialMovingAverage<'_, T> {}
+
+// The running total after each value, in T::Wide so integer sums can't overflow.
+pub fn cumulative_sum<T: Numeric>(v: &[T]) -> CumulativeSum<'_, T> {
+    CumulativeSum {
+        values: v.iter(),
+        sum: T::Wide::default(),
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CumulativeSum<'a, T: Numeric> {
+    values: std::slice::Iter<'a, T>,
+    sum: T::Wide,
+}
+
+impl<T: Numeric> Iterator for CumulativeSum<'_, T> {
+    type Item = T::Wide;
+
+    fn next(&mut self) -> Option<T::Wide> {
+        self.sum = self.sum + self.values.next()?.widen();
+        Some(self.sum)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.values.size_hint()
+    }
+}
+
+impl<T: Numeric> ExactSizeIterator for CumulativeSum<'_, T> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maths;
+
+    const V: [i32; 7] = [2, 4, 6, 8, 10, 9, 3];
+
+    #[test]
+    fn moving_average_yields_once_the_window_is_full() {
+        let averages: Vec<f64> = moving_average(&V, 3).collect();
+
+        assert_eq!(averages, [4.0, 6.0, 8.0, 9.0, 22.0 / 3.0]);
+        assert_eq!(moving_average(&V, 3).len(), 5);
+        assert_eq!(moving_average(&V, 7).collect::<Vec<_>>(), [6.0]);
+        assert_eq!(moving_average(&V, 8).count(), 0);
+        assert_eq!(moving_average::<i32>(&[], 1).count(), 0);
+    }
+
+    #[test]
+    fn moving_average_matches_mean_of_each_window() {
+        let v: Vec<i64> = (0..200).map(|i| (i * 7919) % 101 - 50).collect();
+
+        for window in [1, 2, 5, 17] {
+            let expected: Vec<f64> = v.windows(window).map(|w| maths::mean(w).unwrap()).collect();
+            let actual: Vec<f64> = moving_average(&v, window).collect();
+            assert_eq!(actual.len(), expected.len());
+            for (a, e) in actual.iter().zip(&expected) {
+                assert!((a - e).abs() < 1e-9, "window {window}: {a} vs {e}");
+            }
+        }
+    }
+
+    #[test]
+    fn moving_average_of_floats_matches_mean_of_each_window() {
+        let mut rng = maths::Rng::new(3);
+        let v: Vec<f64> = (0..2000).map(|_| (rng.next_f64() - 0.5) * 1e6).collect();
+
+        for window in [1, 3, 64, 500] {
+            let expected = v.windows(window).map(|w| maths::mean(w).unwrap());
+            for (a, e) in moving_average(&v, window).zip(expected) {
+                assert!(
+                    (a - e).abs() <= 1e-9 * e.abs().max(1.0),
+                    "window {window}: {a} vs {e}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn moving_average_recovers_after_nan_leaves_the_window() {
+        let v = [1.0, f64::NAN, 3.0, 5.0, 7.0, f64::INFINITY, 1.0, 1.0];
+        let averages: Vec<f64> = moving_average(&v, 2).collect();
+
+        assert!(averages[0].is_nan() && averages[1].is_nan());
+        assert_eq!(averages[2..4], [4.0, 6.0]);
+        assert!(averages[4].is_infinite() && averages[5].is_infinite());
+        assert_eq!(averages[6], 1.0);
+    }
+
+    #[test]
+    fn moving_average_of_floats_doesnt_drift() {
+        let averages: Vec<f64> = moving_average(&[1e17, 1.0, 1.0, 1.0], 2).collect();
+        assert_eq!(averages, [5e16, 1.0, 1.0]);
+
+        // Big and small values mixed, as integers that are exact in f64, so the float averages
+        // can be checked against the exact integer ones.
+        let exact: Vec<i64> = (0..300)
+            .map(|i| match i % 3 {
+                0 => 100_000_000_000_000_000 * (i % 7 - 3),
+                _ => i % 11 - 5,
+            })
+            .collect();
+        let floats: Vec<f64> = exact.iter().map(|&x| x as f64).collect();
+        for window in [2, 3, 10] {
+            assert!(
+                moving_average(&floats, window).eq(moving_average(&exact, window)),
+                "window {window}"
+            );
+        }
+
+        let huge: Vec<i128> = vec![i128::MAX / 4, 1, 1, 1];
+        assert_eq!(moving_average(&huge, 2).last(), Some(1.0));
+    }
+
+    #[test]
+    fn weighted_moving_average_works() {
+        let averages: Vec<f64> = weighted_moving_average(&V, &[1.0, 2.0, 3.0]).collect();
+
+        // (2 + 8 + 18) / 6, (4 + 12 + 24) / 6, ...
+        assert_eq!(averages[..2], [28.0 / 6.0, 40.0 / 6.0]);
+        assert_eq!(averages.len(), 5);
+
+        // Equal weights are the simple moving average.
+        let equal: Vec<f64> = weighted_moving_average(&V, &[1.0; 3]).collect();
+        let simple: Vec<f64> = moving_average(&V, 3).collect();
+        for (a, b) in equal.iter().zip(&simple) {
+            assert!((a - b).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn exponential_moving_average_starts_from_the_first_value() {
+        let averages: Vec<f64> = exponential_moving_average(&[10, 20, 20, 0], 0.5).collect();
+
+        assert_eq!(averages, [10.0, 15.0, 17.5, 8.75]);
+        // alpha = 1 just follows the input.
+        assert!(exponential_moving_average(&V, 1.0).eq(V.iter().map(|&x| x as f64)));
+    }
+
+    #[test]
+    fn cumulative_sum_works() {
+        assert!(cumulative_sum(&V).eq([2, 6, 12, 20, 30, 39, 42]));
+        assert!(cumulative_sum(&[u64::MAX, u64::MAX]).eq([u64::MAX as u128, 2 * u64::MAX as u128]));
+    }
+
+    #[test]
+    fn iterators_chain() {
+        // Smooth, then smooth again, then find the biggest smoothed value.
+        let smoothed: Vec<f64> = moving_average(&V, 2).collect();
+        let twice = exponential_moving_average(&smoothed, 0.5);
+
+        assert_eq!(twice.len(), 6);
+        assert!(twice.fold(f64::MIN, f64::max) < 10.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "window must be non-zero")]
+    fn zero_window_panics() {
+        moving_average(&V, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "alpha must be in (0, 1]")]
+    fn bad_alpha_panics() {
+        exponential_moving_average(&V, 0.0);
+    }
+}
@@ -0,0 +1,434 @@
+use super::error::{check_not_empty, check_result};
+use super::nan::first_nan;
+use super::{NanPolicy, Numeric, RunningStats, StatsError};
+
+// The variances (and the mean of floats) push everything through RunningStats, so they agree
+// exactly with a RunningStats fed the same values.
+fn running_stats<T: Numeric>(v: &[T], nan: NanPolicy) -> RunningStats<T> {
+    let mut stats = RunningStats::with_nan_policy(nan);
+    stats.extend(v.iter().copied());
+    stats
+}
+
+fn wide_sum<T: Numeric>(v: &[T]) -> T::Wide {
+    v.iter().fold(T::Wide::default(), |sum, &x| sum + x.widen())
+}
+
+// Everything below fails with StatsError::Empty for an empty slice, the same way median and
+// mode_n do. The f64 results are NonFinite if a NaN or infinity in `v` poisoned them, and
+// Overflow if `v` was all finite but the arithmetic still ran off the end of f64. Each one has a
+// *_with version that takes a NanPolicy; the plain ones use NanPolicy::Error.
+
+// Summed in T::Wide (i128/u128 for integers), so even a sum of i64::MAX values can't overflow.
+// For floats the sum is itself an f64, so it's checked like the other results.
+pub fn sum<T: Numeric>(v: &[T]) -> Result<T::Wide, StatsError> {
+    sum_with(v, NanPolicy::Error)
+}
+
+pub fn sum_with<T: Numeric>(v: &[T], nan: NanPolicy) -> Result<T::Wide, StatsError> {
+    let v = nan.filter(v)?;
+    check_not_empty(&v)?;
+
+    let sum = wide_sum(&v);
+    check_result(T::wide_to_f64(sum), &v, nan)?;
+    Ok(sum)
+}
+
+// For integers (other than 128-bit ones) this is the exact sum divided by the count.
+pub fn mean<T: Numeric>(v: &[T]) -> Result<f64, StatsError> {
+    mean_with(v, NanPolicy::Error)
+}
+
+pub fn mean_with<T: Numeric>(v: &[T], nan: NanPolicy) -> Result<f64, StatsError> {
+    let v = nan.filter(v)?;
+    // When T::EXACT_WIDE the sum is exact, and dividing it by the count rounds once, where
+    // Welford's running mean rounds at every value.
+    let mean = if T::EXACT_WIDE {
+        check_not_empty(&v)?;
+        T::wide_to_f64(wide_sum(&v)) / v.len() as f64
+    } else {
+        running_stats(&v, nan).mean()?
+    };
+    check_result(mean, &v, nan)
+}
+
+// Divides by n. Use this when `v` is the whole population.
+pub fn population_variance<T: Numeric>(v: &[T]) -> Result<f64, StatsError> {
+    population_variance_with(v, NanPolicy::Error)
+}
+
+pub fn population_variance_with<T: Numeric>(v: &[T], nan: NanPolicy) -> Result<f64, StatsError> {
+    let v = nan.filter(v)?;
+    let variance = running_stats(&v, nan).population_variance()?;
+    check_result(variance, &v, nan)
+}
+
+// Divides by n - 1. Use this when `v` is a sample of a bigger population. Needs at least two
+// values (after skipping NaNs, for Skip).
+pub fn sample_variance<T: Numeric>(v: &[T]) -> Result<f64, StatsError> {
+    sample_variance_with(v, NanPolicy::Error)
+}
+
+pub fn sample_variance_with<T: Numeric>(v: &[T], nan: NanPolicy) -> Result<f64, StatsError> {
+    let v = nan.filter(v)?;
+    check_not_empty(&v)?;
+
+    let variance = running_stats(&v, nan).sample_variance()?;
+    check_result(variance, &v, nan)
+}
+
+pub fn population_stddev<T: Numeric>(v: &[T]) -> Result<f64, StatsError> {
+    population_stddev_with(v, NanPolicy::Error)
+}
+
+pub fn population_stddev_with<T: Numeric>(v: &[T], nan: NanPolicy) -> Result<f64, StatsError> {
+    Ok(population_variance_with(v, nan)?.sqrt())
+}
+
+pub fn sample_stddev<T: Numeric>(v: &[T]) -> Result<f64, StatsError> {
+    sample_stddev_with(v, NanPolicy::Error)
+}
+
+pub fn sample_stddev_with<T: Numeric>(v: &[T], nan: NanPolicy) -> Result<f64, StatsError> {
+    Ok(sample_variance_with(v, nan)?.sqrt())
+}
+
+pub fn min<T: Numeric>(v: &[T]) -> Result<T, StatsError> {
+    min_with(v, NanPolicy::Error)
+}
+
+// Under Propagate, a NaN anywhere is the min (and the max).
+pub fn min_with<T: Numeric>(v: &[T], nan: NanPolicy) -> Result<T, StatsError> {
+    let v = nan.filter(v)?;
+    if let Some(nan) = first_nan(&v) {
+        return Ok(nan);
+    }
+
+    v.iter()
+        .copied()
+        .min_by(T::total_cmp)
+        .ok_or(StatsError::Empty)
+}
+
+pub fn max<T: Numeric>(v: &[T]) -> Result<T, StatsError> {
+    max_with(v, NanPolicy::Error)
+}
+
+pub fn max_with<T: Numeric>(v: &[T], nan: NanPolicy) -> Result<T, StatsError> {
+    let v = nan.filter(v)?;
+    if let Some(nan) = first_nan(&v) {
+        return Ok(nan);
+    }
+
+    v.iter()
+        .copied()
+        .max_by(T::total_cmp)
+        .ok_or(StatsError::Empty)
+}
+
+// max - min, in T::Wide so i32::MAX - i32::MIN doesn't overflow.
+pub fn range<T: Numeric>(v: &[T]) -> Result<T::Wide, StatsError> {
+    range_with(v, NanPolicy::Error)
+}
+
+pub fn range_with<T: Numeric>(v: &[T], nan: NanPolicy) -> Result<T::Wide, StatsError> {
+    let v = nan.filter(v)?;
+    let range = max_with(&v, nan)?.widen() - min_with(&v, nan)?.widen();
+    check_result(T::wide_to_f64(range), &v, nan)?;
+    Ok(range)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Summary<T: Numeric> {
+    pub count: usize,
+    pub sum: T::Wide,
+    pub min: T,
+    pub max: T,
+    pub range: T::Wide,
+    pub mean: f64,
+    pub population_variance: f64,
+    // None for a single value.
+    pub sample_variance: Option<f64>,
+}
+
+impl<T: Numeric> Summary<T> {
+    pub fn population_stddev(&self) -> f64 {
+        self.population_variance.sqrt()
+    }
+
+    pub fn sample_stddev(&self) -> Option<f64> {
+        Some(self.sample_variance?.sqrt())
+    }
+}
+
+// All of the above in a single pass over `v` (plus a scan for NaN). Fails if any of the
+// individual results would, except that a single value just gets no sample variance.
+pub fn describe<T: Numeric>(v: &[T]) -> Result<Summary<T>, StatsError> {
+    describe_with(v, NanPolicy::Error)
+}
+
+// Under Propagate, a NaN makes min, max and everything computed from the values NaN.
+pub fn describe_with<T: Numeric>(v: &[T], nan: NanPolicy) -> Result<Summary<T>, StatsError> {
+    let v = nan.filter(v)?;
+    check_not_empty(&v)?;
+
+    let mut stats = RunningStats::with_nan_policy(nan);
+    let mut sum = T::Wide::default();
+    for &x in v.iter() {
+        stats.push(x);
+        sum = sum + x.widen();
+    }
+
+    summarize(&stats, sum, &v, nan)
+}
+
+// Finishes describe_with from the accumulated stats and sum of `v`, which has already been
+// through nan.filter. par_describe accumulates in chunks and finishes here too.
+pub(crate) fn summarize<T: Numeric>(
+    stats: &RunningStats<T>,
+    sum: T::Wide,
+    v: &[T],
+    nan: NanPolicy,
+) -> Result<Summary<T>, StatsError> {
+    let (min, max) = (stats.min()?, stats.max()?);
+    let range = max.widen() - min.widen();
+    check_result(T::wide_to_f64(sum), v, nan)?;
+    check_result(T::wide_to_f64(range), v, nan)?;
+    // The same exact mean as mean_with.
+    let mean = if T::EXACT_WIDE {
+        T::wide_to_f64(sum) / v.len() as f64
+    } else {
+        stats.mean()?
+    };
+    let mean = check_result(mean, v, nan)?;
+    let population_variance = check_result(stats.population_variance()?, v, nan)?;
+    let sample_variance = match stats.sample_variance() {
+        Err(StatsError::NotEnoughValues { .. }) => None,
+        variance => Some(check_result(variance?, v, nan)?),
+    };
+
+    Ok(Summary {
+        // Under Propagate, the NaNs count too.
+        count: v.len(),
+        sum,
+        min,
+        max,
+        range,
+        mean,
+        population_variance,
+        sample_variance,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The example from https://en.wikipedia.org/wiki/Standard_deviation: mean 5, population
+    // stddev 2.
+    const V: [i32; 8] = [2, 4, 4, 4, 5, 5, 7, 9];
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-9,
+            "expected {expected}, got {actual}"
+        );
+    }
+
+    #[test]
+    fn sum_and_mean_work() {
+        assert_eq!(sum(&V).unwrap(), 40);
+        assert_eq!(mean(&V).unwrap(), 5.0);
+    }
+
+    #[test]
+    fn integer_mean_is_the_exact_sum_over_the_count() {
+        // Sums to 6000, but a running mean picks up rounding error along the way.
+        let v: Vec<i32> = (0..1000).map(|i| i * 7 % 13).collect();
+        let summary = describe(&v).unwrap();
+
+        assert_eq!(summary.sum, 6000);
+        assert_eq!(summary.mean, 6.0);
+        assert_eq!(mean(&v), Ok(6.0));
+        assert_eq!(crate::maths::par_describe(&v, 2).unwrap().mean, 6.0);
+    }
+
+    #[test]
+    fn variance_and_stddev_work() {
+        assert_eq!(population_variance(&V).unwrap(), 4.0);
+        assert_eq!(population_stddev(&V).unwrap(), 2.0);
+        assert_close(sample_variance(&V).unwrap(), 32.0 / 7.0);
+        assert_close(sample_stddev(&V).unwrap(), (32.0f64 / 7.0).sqrt());
+    }
+
+    #[test]
+    fn sample_variance_needs_two_values() {
+        assert_eq!(population_variance(&[3]).unwrap(), 0.0);
+        assert_eq!(
+            sample_variance(&[3]),
+            Err(StatsError::NotEnoughValues { needed: 2, got: 1 })
+        );
+        assert_eq!(
+            sample_stddev(&[3]),
+            Err(StatsError::NotEnoughValues { needed: 2, got: 1 })
+        );
+    }
+
+    #[test]
+    fn min_max_and_range_work() {
+        assert_eq!(min(&V).unwrap(), 2);
+        assert_eq!(max(&V).unwrap(), 9);
+        assert_eq!(range(&V).unwrap(), 7);
+    }
+
+    #[test]
+    fn sum_does_not_overflow() {
+        let v = [i64::MAX; 4];
+        let u = [u64::MAX; 3];
+
+        assert_eq!(sum(&v).unwrap(), 4 * i64::MAX as i128);
+        assert_eq!(sum(&u).unwrap(), 3 * u64::MAX as u128);
+        assert_eq!(mean(&v).unwrap(), i64::MAX as f64);
+    }
+
+    #[test]
+    fn range_does_not_overflow() {
+        assert_eq!(range(&[i32::MIN, i32::MAX]).unwrap(), u32::MAX as i128);
+    }
+
+    #[test]
+    fn variance_is_stable_with_a_large_offset() {
+        // Same spread as V, shifted by 10^9. The naive sum-of-squares formula works with values
+        // around 10^18, where an f64 can't even represent the answer's integer part.
+        let v: Vec<i64> = V.iter().map(|&x| x as i64 + 1_000_000_000).collect();
+
+        assert!((population_variance(&v).unwrap() - 4.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn works_with_floats() {
+        let v = [1.5, -0.5, 2.0];
+
+        assert_eq!(sum(&v).unwrap(), 3.0);
+        assert_eq!(mean(&v).unwrap(), 1.0);
+        assert_eq!(min(&v).unwrap(), -0.5);
+        assert_eq!(range(&v).unwrap(), 2.5);
+    }
+
+    #[test]
+    fn everything_returns_empty_error_with_empty_vector() {
+        let v: Vec<i32> = vec![];
+
+        assert_eq!(sum(&v), Err(StatsError::Empty));
+        assert_eq!(mean(&v), Err(StatsError::Empty));
+        assert_eq!(population_variance(&v), Err(StatsError::Empty));
+        assert_eq!(sample_variance(&v), Err(StatsError::Empty));
+        assert_eq!(population_stddev(&v), Err(StatsError::Empty));
+        assert_eq!(sample_stddev(&v), Err(StatsError::Empty));
+        assert_eq!(min(&v), Err(StatsError::Empty));
+        assert_eq!(max(&v), Err(StatsError::Empty));
+        assert_eq!(range(&v), Err(StatsError::Empty));
+        assert_eq!(describe(&v), Err(StatsError::Empty));
+    }
+
+    #[test]
+    fn nan_and_infinity_are_non_finite() {
+        let nan = [1.0, f64::NAN];
+        let inf = [1.0, f64::INFINITY];
+
+        assert_eq!(sum(&nan), Err(StatsError::NonFinite));
+        assert_eq!(mean(&inf), Err(StatsError::NonFinite));
+        assert_eq!(population_variance(&inf), Err(StatsError::NonFinite));
+        assert_eq!(min(&nan), Err(StatsError::NonFinite));
+        assert_eq!(describe(&nan), Err(StatsError::NonFinite));
+        // Order statistics are fine with infinities.
+        assert_eq!(max(&inf).unwrap(), f64::INFINITY);
+    }
+
+    #[test]
+    fn finite_values_can_still_overflow() {
+        let v = [f64::MAX, f64::MAX];
+
+        assert_eq!(sum(&v), Err(StatsError::Overflow));
+        assert_eq!(range(&[-f64::MAX, f64::MAX]), Err(StatsError::Overflow));
+        assert_eq!(describe(&v), Err(StatsError::Overflow));
+    }
+
+    #[test]
+    fn describe_matches_the_individual_functions() {
+        let summary = describe(&V).unwrap();
+
+        assert_eq!(summary.count, V.len());
+        assert_eq!(summary.sum, sum(&V).unwrap());
+        assert_eq!(summary.min, min(&V).unwrap());
+        assert_eq!(summary.max, max(&V).unwrap());
+        assert_eq!(summary.range, range(&V).unwrap());
+        assert_eq!(summary.mean, mean(&V).unwrap());
+        assert_eq!(
+            summary.population_variance,
+            population_variance(&V).unwrap()
+        );
+        assert_eq!(summary.sample_variance, sample_variance(&V).ok());
+        assert_eq!(summary.population_stddev(), 2.0);
+    }
+
+    #[test]
+    fn describe_works_with_one_value() {
+        let summary = describe(&[7u8]).unwrap();
+
+        assert_eq!(summary.sum, 7);
+        assert_eq!(summary.range, 0);
+        assert_eq!(summary.population_variance, 0.0);
+        assert_eq!(summary.sample_stddev(), None);
+    }
+
+    #[test]
+    fn nan_policy_is_honoured() {
+        let v = [1.0, f64::NAN, 3.0];
+
+        assert_eq!(mean_with(&v, NanPolicy::Skip), Ok(2.0));
+        assert_eq!(sum_with(&v, NanPolicy::Skip), Ok(4.0));
+        assert_eq!(min_with(&v, NanPolicy::Skip), Ok(1.0));
+        assert_eq!(range_with(&v, NanPolicy::Skip), Ok(2.0));
+        assert_eq!(
+            sample_variance_with(&[1.0, f64::NAN], NanPolicy::Skip),
+            Err(StatsError::NotEnoughValues { needed: 2, got: 1 })
+        );
+        assert_eq!(mean_with(&v, NanPolicy::Error), Err(StatsError::NonFinite));
+
+        assert!(mean_with(&v, NanPolicy::Propagate).unwrap().is_nan());
+        assert!(max_with(&v, NanPolicy::Propagate).unwrap().is_nan());
+        assert!(population_stddev_with(&v, NanPolicy::Propagate)
+            .unwrap()
+            .is_nan());
+    }
+
+    #[test]
+    fn propagate_passes_infinities_through() {
+        let v = [1.0, f64::INFINITY];
+
+        assert_eq!(mean_with(&v, NanPolicy::Propagate), Ok(f64::INFINITY));
+        assert_eq!(mean_with(&v, NanPolicy::Skip), Err(StatsError::NonFinite));
+        // Finite input that overflows is still an error.
+        assert_eq!(
+            sum_with(&[f64::MAX, f64::MAX], NanPolicy::Propagate),
+            Err(StatsError::Overflow)
+        );
+    }
+
+    #[test]
+    fn describe_with_skips_or_propagates_nan() {
+        let v = [2.0, 4.0, f64::NAN, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0];
+
+        let skipped = describe_with(&v, NanPolicy::Skip).unwrap();
+        assert_eq!(skipped.count, 8);
+        assert_eq!(skipped.mean, 5.0);
+        assert_eq!(skipped.population_stddev(), 2.0);
+
+        let propagated = describe_with(&v, NanPolicy::Propagate).unwrap();
+        assert_eq!(propagated.count, 9);
+        assert!(propagated.min.is_nan() && propagated.max.is_nan());
+        assert!(propagated.mean.is_nan());
+        assert!(propagated.sample_variance.unwrap().is_nan());
+    }
+}
@@ -0,0 +1,378 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use super::error::check_result;
+use super::quantile::check_quantile;
+use super::{Interpolation, Modes, NanPolicy, Numeric, RunningStats, StatsError, TotalOrd};
+
+// Statistics over pre-aggregated (value, count) pairs, without expanding them into one entry per
+// count. Each gives the same answer as the unweighted function on the expanded values, e.g.
+// weighted_median(&[(3, 2), (1, 1)]) == median(&[3, 3, 1]), and the unweighted functions are
+// the case where every count is 1. A value can show up in more than one pair, and pairs with a
+// count of 0 are ignored.
+//
+// Counts can be any integer type. A negative count is InvalidWeight, and a total count of 0 is
+// Empty. NaN values are NonFinite, as they are for the plain functions, and the *_with versions
+// take a NanPolicy instead. Under Skip, a NaN's count is dropped along with it.
+
+// The pairs with a non-zero count, with the counts as usizes, and the total count.
+fn counted<T: Copy, W: Copy + TryInto<usize>>(
+    pairs: &[(T, W)],
+) -> Result<(Vec<(T, usize)>, usize), StatsError> {
+    let mut counted = Vec::with_capacity(pairs.len());
+    let mut total: usize = 0;
+    for &(x, w) in pairs {
+        let count: usize = w.try_into().map_err(|_| StatsError::InvalidWeight)?;
+        if count > 0 {
+            total = total.checked_add(count).ok_or(StatsError::Overflow)?;
+            counted.push((x, count));
+        }
+    }
+
+    if total == 0 {
+        return Err(StatsError::Empty);
+    }
+    Ok((counted, total))
+}
+
+// counted, with `nan` applied to the values.
+fn counted_with<T: Numeric, W: Copy + TryInto<usize>>(
+    pairs: &[(T, W)],
+    nan: NanPolicy,
+) -> Result<(Vec<(T, usize)>, usize), StatsError> {
+    let (mut counted, mut total) = counted(pairs)?;
+    if !counted.iter().any(|(x, _)| x.is_nan()) {
+        return Ok((counted, total));
+    }
+
+    match nan {
+        NanPolicy::Propagate => {}
+        NanPolicy::Skip => {
+            counted.retain(|(x, _)| !x.is_nan());
+            total = counted.iter().map(|&(_, count)| count).sum();
+            if total == 0 {
+                return Err(StatsError::Empty);
+            }
+        }
+        NanPolicy::Error => return Err(StatsError::NonFinite),
+    }
+    Ok((counted, total))
+}
+
+// Goes through RunningStats::push_n, so it matches mean() on the expanded values up to rounding.
+pub fn weighted_mean<T: Numeric, W: Copy + TryInto<usize>>(
+    pairs: &[(T, W)],
+) -> Result<f64, StatsError> {
+    weighted_mean_with(pairs, NanPolicy::Error)
+}
+
+pub fn weighted_mean_with<T: Numeric, W: Copy + TryInto<usize>>(
+    pairs: &[(T, W)],
+    nan: NanPolicy,
+) -> Result<f64, StatsError> {
+    let (counted, _) = counted_with(pairs, nan)?;
+
+    let mut stats = RunningStats::with_nan_policy(nan);
+    for &(x, count) in &counted {
+        stats.push_n(x, count);
+    }
+
+    let mean = stats.mean()?;
+    let values: Vec<T> = counted.iter().map(|&(x, _)| x).collect();
+    check_result(mean, &values, nan)
+}
+
+pub fn weighted_median<T: Numeric, W: Copy + TryInto<usize>>(
+    pairs: &[(T, W)],
+) -> Result<f64, StatsError> {
+    weighted_median_with(pairs, NanPolicy::Error)
+}
+
+pub fn weighted_median_with<T: Numeric, W: Copy + TryInto<usize>>(
+    pairs: &[(T, W)],
+    nan: NanPolicy,
+) -> Result<f64, StatsError> {
+    weighted_quantile_with(pairs, 0.5, Interpolation::Midpoint, nan)
+}
+
+pub fn weighted_quantile<T: Numeric, W: Copy + TryInto<usize>>(
+    pairs: &[(T, W)],
+    q: f64,
+    method: Interpolation,
+) -> Result<f64, StatsError> {
+    weighted_quantile_with(pairs, q, method, NanPolicy::Error)
+}
+
+pub fn weighted_quantile_with<T: Numeric, W: Copy + TryInto<usize>>(
+    pairs: &[(T, W)],
+    q: f64,
+    method: Interpolation,
+    nan: NanPolicy,
+) -> Result<f64, StatsError> {
+    Ok(weighted_quantiles_with(pairs, &[q], method, nan)?[0])
+}
+
+// Sorts the pairs instead of the expanded values, so it's O(p lg p) for p pairs however big the
+// counts are.
+pub fn weighted_quantiles<T: Numeric, W: Copy + TryInto<usize>>(
+    pairs: &[(T, W)],
+    qs: &[f64],
+    method: Interpolation,
+) -> Result<Vec<f64>, StatsError> {
+    weighted_quantiles_with(pairs, qs, method, NanPolicy::Error)
+}
+
+// Under Propagate, a NaN makes every quantile that NaN, as for quantiles_with.
+pub fn weighted_quantiles_with<T: Numeric, W: Copy + TryInto<usize>>(
+    pairs: &[(T, W)],
+    qs: &[f64],
+    method: Interpolation,
+    nan: NanPolicy,
+) -> Result<Vec<f64>, StatsError> {
+    let (mut counted, total) = counted_with(pairs, nan)?;
+    for &q in qs {
+        check_quantile(q)?;
+    }
+    if let Some(&(nan, _)) = counted.iter().find(|(x, _)| x.is_nan()) {
+        return Ok(vec![nan.to_f64(); qs.len()]);
+    }
+
+    counted.sort_by(|a, b| a.0.total_cmp(&b.0));
+    // ends[i] is the expanded index just past counted[i]'s copies, so expanded index j falls in
+    // the first pair whose end is past j.
+    let ends: Vec<usize> = counted
+        .iter()
+        .scan(0, |end, &(_, count)| {
+            *end += count;
+            Some(*end)
+        })
+        .collect();
+    let value_at = |j: usize| counted[ends.partition_point(|&end| end <= j)].0.to_f64();
+
+    qs.iter()
+        .map(|&q| {
+            let (lower_index, upper_index, fraction) = method.positions(total, q);
+            method.combine(value_at(lower_index), value_at(upper_index), fraction)
+        })
+        .collect()
+}
+
+pub fn weighted_mode<T: Hash + Ord + Clone, W: Copy + TryInto<usize>>(
+    pairs: &[(T, W)],
+) -> Result<Modes<T>, StatsError> {
+    let mut value_counts: HashMap<&T, usize> = HashMap::new();
+    for (x, w) in pairs {
+        let count: usize = (*w).try_into().map_err(|_| StatsError::InvalidWeight)?;
+        if count > 0 {
+            let total = value_counts.entry(x).or_insert(0);
+            *total = total.checked_add(count).ok_or(StatsError::Overflow)?;
+        }
+    }
+
+    Modes::from_counts(value_counts)
+}
+
+// The weighted modes of floats (or any Numeric), compared by their total order like mode_n_with.
+// Under Propagate a NaN anywhere makes the mode just that NaN, with the NaNs' total count.
+pub fn weighted_mode_with<T: Numeric, W: Copy + TryInto<usize>>(
+    pairs: &[(T, W)],
+    nan: NanPolicy,
+) -> Result<Modes<T>, StatsError> {
+    let (counted, _) = counted_with(pairs, nan)?;
+    if let Some(&(nan, _)) = counted.iter().find(|(x, _)| x.is_nan()) {
+        return Ok(Modes {
+            values: vec![nan],
+            count: counted
+                .iter()
+                .filter(|(x, _)| x.is_nan())
+                .map(|&(_, count)| count)
+                .sum(),
+        });
+    }
+
+    let wrapped: Vec<(TotalOrd<T>, usize)> = counted
+        .into_iter()
+        .map(|(x, count)| (TotalOrd(x), count))
+        .collect();
+    let modes = weighted_mode(&wrapped)?;
+    Ok(Modes {
+        values: modes.values.into_iter().map(|TotalOrd(x)| x).collect(),
+        count: modes.count,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maths;
+
+    const PAIRS: [(i32, u32); 6] = [(5, 2), (-1, 1), (3, 4), (8, 0), (5, 1), (10, 2)];
+
+    fn expand<T: Copy, W: Copy + TryInto<usize>>(pairs: &[(T, W)]) -> Vec<T> {
+        pairs
+            .iter()
+            .flat_map(|&(x, w)| std::iter::repeat_n(x, w.try_into().ok().unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn mean_matches_expanded() {
+        let expected = maths::mean(&expand(&PAIRS)).unwrap();
+
+        assert!((weighted_mean(&PAIRS).unwrap() - expected).abs() < 1e-9);
+    }
+
+    #[test]
+    fn median_and_quantiles_match_expanded() {
+        let expanded = expand(&PAIRS);
+        let qs = [0.0, 0.1, 0.25, 0.5, 0.6, 0.9, 1.0];
+
+        assert_eq!(weighted_median(&PAIRS), maths::median(&expanded));
+        for method in [
+            Interpolation::NearestRank,
+            Interpolation::Linear,
+            Interpolation::Lower,
+            Interpolation::Higher,
+            Interpolation::Midpoint,
+        ] {
+            assert_eq!(
+                weighted_quantiles(&PAIRS, &qs, method),
+                maths::quantiles(&expanded, &qs, method),
+                "{method:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn mode_matches_expanded() {
+        // 5 shows up in two pairs, for a total of 3. 3 has 4.
+        assert_eq!(weighted_mode(&PAIRS), maths::mode_n(&expand(&PAIRS)));
+        assert_eq!(
+            weighted_mode(&[(2, 2), (1, 2)]).unwrap(),
+            Modes {
+                values: vec![1, 2],
+                count: 2
+            }
+        );
+    }
+
+    #[test]
+    fn weight_one_is_the_unweighted_case() {
+        let v = [4.5, -2.0, 7.25, 4.5, 0.0];
+        let pairs: Vec<(f64, u8)> = v.iter().map(|&x| (x, 1)).collect();
+
+        assert_eq!(weighted_median(&pairs), maths::median(&v));
+        assert_eq!(
+            weighted_quantile(&pairs, 0.3, Interpolation::Linear),
+            maths::quantile(&v, 0.3, Interpolation::Linear)
+        );
+        assert!((weighted_mean(&pairs).unwrap() - maths::mean(&v).unwrap()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn huge_counts_are_not_expanded() {
+        let pairs = [(1u8, 1_000_000_000_000u64), (2, 1_000_000_000_001)];
+
+        assert_eq!(weighted_median(&pairs), Ok(2.0));
+        assert_eq!(weighted_mode(&pairs).unwrap().count, 1_000_000_000_001);
+    }
+
+    #[test]
+    fn counts_past_f64_precision_stay_in_bounds() {
+        let pairs = [(1u8, 1u64 << 60), (2, 256)];
+
+        for method in [Interpolation::Linear, Interpolation::Higher] {
+            assert_eq!(
+                weighted_quantile(&pairs, 1.0, method),
+                Ok(2.0),
+                "{method:?}"
+            );
+        }
+        assert_eq!(
+            weighted_quantile(&pairs, 1.0, Interpolation::NearestRank),
+            Ok(2.0)
+        );
+        assert_eq!(weighted_median(&pairs), Ok(1.0));
+    }
+
+    #[test]
+    fn nan_policies() {
+        let pairs = [
+            (2.5, 3u32),
+            (f64::NAN, 2),
+            (-1.0, 1),
+            (2.5, 1),
+            (f64::NAN, 4),
+        ];
+        let clean = [(2.5, 3u32), (-1.0, 1), (2.5, 1)];
+
+        assert_eq!(
+            weighted_mean_with(&pairs, NanPolicy::Skip),
+            weighted_mean(&clean)
+        );
+        assert_eq!(
+            weighted_median_with(&pairs, NanPolicy::Skip),
+            weighted_median(&clean)
+        );
+        assert_eq!(
+            weighted_quantiles_with(&pairs, &[0.1, 0.9], Interpolation::Linear, NanPolicy::Skip),
+            weighted_quantiles(&clean, &[0.1, 0.9], Interpolation::Linear)
+        );
+        assert_eq!(
+            weighted_mode_with(&pairs, NanPolicy::Skip).unwrap(),
+            Modes {
+                values: vec![2.5],
+                count: 4
+            }
+        );
+
+        assert!(weighted_mean_with(&pairs, NanPolicy::Propagate)
+            .unwrap()
+            .is_nan());
+        assert!(
+            weighted_quantile_with(&pairs, 0.0, Interpolation::Lower, NanPolicy::Propagate)
+                .unwrap()
+                .is_nan()
+        );
+        let modes = weighted_mode_with(&pairs, NanPolicy::Propagate).unwrap();
+        assert!(modes.values.len() == 1 && modes.values[0].is_nan());
+        assert_eq!(modes.count, 6);
+
+        let all_nan = [(f64::NAN, 1), (1.0, 0)];
+        assert_eq!(
+            weighted_mode_with(&all_nan, NanPolicy::Skip),
+            Err(StatsError::Empty)
+        );
+        assert_eq!(
+            weighted_mode_with(&all_nan, NanPolicy::Error),
+            Err(StatsError::NonFinite)
+        );
+        // A NaN with a count of 0 is ignored like any other such pair.
+        assert_eq!(weighted_median(&[(f64::NAN, 0), (1.0, 1)]), Ok(1.0));
+    }
+
+    #[test]
+    fn bad_input_is_an_error() {
+        assert_eq!(weighted_mean::<i32, u32>(&[]), Err(StatsError::Empty));
+        assert_eq!(weighted_median(&[(1, 0), (2, 0)]), Err(StatsError::Empty));
+        assert_eq!(weighted_mode(&[(1, 0)]), Err(StatsError::Empty));
+        assert_eq!(
+            weighted_mean(&[(1, 2), (2, -1)]),
+            Err(StatsError::InvalidWeight)
+        );
+        assert_eq!(weighted_mode(&[(1, -1)]), Err(StatsError::InvalidWeight));
+        assert_eq!(
+            weighted_quantile(&[(1, 1)], 1.5, Interpolation::Linear),
+            Err(StatsError::InvalidQuantile(1.5))
+        );
+        assert_eq!(
+            weighted_median(&[(f64::NAN, 1)]),
+            Err(StatsError::NonFinite)
+        );
+        assert_eq!(weighted_mean(&[(f64::NAN, 1)]), Err(StatsError::NonFinite));
+        assert_eq!(
+            weighted_median(&[(1, usize::MAX), (2, 1)]),
+            Err(StatsError::Overflow)
+        );
+    }
+}
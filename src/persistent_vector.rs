@@ -0,0 +1,270 @@
+// An immutable vector that shares structure between versions, the same way the cons lists in
+// ref_cells.rs share their tails. The values live in a trie of 32-wide nodes (like Clojure's
+// PersistentVector), so get/set/push_back only walk log32(n) levels, and set/push_back only copy
+// the nodes along that one path. Every other node is an Rc shared with the previous version.
+// https://hypirion.com/musings/understanding-persistent-vector-pt-1
+use std::fmt;
+use std::rc::Rc;
+
+const BITS: usize = 5;
+const WIDTH: usize = 1 << BITS;
+const MASK: usize = WIDTH - 1;
+
+#[derive(Debug)]
+enum Node<T> {
+    Branch(Vec<Rc<Node<T>>>),
+    Leaf(Vec<T>),
+}
+
+pub struct PersistentVector<T> {
+    root: Rc<Node<T>>,
+    len: usize,
+    // How far to shift an index to find the root's child. 0 means the root is a leaf.
+    shift: usize,
+}
+
+impl<T> PersistentVector<T> {
+    pub fn new() -> PersistentVector<T> {
+        PersistentVector {
+            root: Rc::new(Node::Leaf(vec![])),
+            len: 0,
+            shift: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn get(&self, index: usize) -> Option<&T> {
+        if index >= self.len {
+            return None;
+        }
+
+        let mut node = &self.root;
+        let mut level = self.shift;
+        loop {
+            match &**node {
+                Node::Branch(children) => {
+                    node = &children[(index >> level) & MASK];
+                    level -= BITS;
+                }
+                Node::Leaf(values) => return values.get(index & MASK),
+            }
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        (0..self.len).filter_map(move |i| self.get(i))
+    }
+}
+
+impl<T: Clone> PersistentVector<T> {
+    // Returns a new version with `index` replaced, or None if `index` is out of bounds.
+    pub fn set(&self, index: usize, value: T) -> Option<PersistentVector<T>> {
+        if index >= self.len {
+            return None;
+        }
+
+        Some(PersistentVector {
+            root: set_in(&self.root, self.shift, index, value),
+            len: self.len,
+            shift: self.shift,
+        })
+    }
+
+    pub fn push_back(&self, value: T) -> PersistentVector<T> {
+        // When the trie is full, the old root becomes the first child of a new, taller root.
+        if self.len == 1 << (self.shift + BITS) {
+            let shift = self.shift + BITS;
+            let root = Node::Branch(vec![Rc::clone(&self.root), new_path(self.shift, value)]);
+            return PersistentVector {
+                root: Rc::new(root),
+                len: self.len + 1,
+                shift,
+            };
+        }
+
+        PersistentVector {
+            root: push_in(&self.root, self.shift, self.len, value),
+            len: self.len + 1,
+            shift: self.shift,
+        }
+    }
+}
+
+fn set_in<T: Clone>(node: &Rc<Node<T>>, level: usize, index: usize, value: T) -> Rc<Node<T>> {
+    match &**node {
+        Node::Branch(children) => {
+            // Cloning the Vec only clones the Rcs, so every sibling is shared with `node`.
+            let mut children = children.clone();
+            let slot = (index >> level) & MASK;
+            children[slot] = set_in(&children[slot], level - BITS, index, value);
+            Rc::new(Node::Branch(children))
+        }
+        Node::Leaf(values) => {
+            let mut values = values.clone();
+            values[index & MASK] = value;
+            Rc::new(Node::Leaf(values))
+        }
+    }
+}
+
+fn push_in<T: Clone>(node: &Rc<Node<T>>, level: usize, index: usize, value: T) -> Rc<Node<T>> {
+    match &**node {
+        Node::Branch(children) => {
+            let mut children = children.clone();
+            let slot = (index >> level) & MASK;
+            match children.get(slot) {
+                Some(child) => children[slot] = push_in(child, level - BITS, index, value),
+                None => children.push(new_path(level - BITS, value)),
+            }
+            Rc::new(Node::Branch(children))
+        }
+        Node::Leaf(values) => {
+            let mut values = values.clone();
+            values.push(value);
+            Rc::new(Node::Leaf(values))
+        }
+    }
+}
+
+// A chain of single-child branches down to a leaf holding just `value`.
+fn new_path<T>(level: usize, value: T) -> Rc<Node<T>> {
+    if level == 0 {
+        Rc::new(Node::Leaf(vec![value]))
+    } else {
+        Rc::new(Node::Branch(vec![new_path(level - BITS, value)]))
+    }
+}
+
+impl<T> Default for PersistentVector<T> {
+    fn default() -> Self {
+        PersistentVector::new()
+    }
+}
+
+// Cloning a version is just another reference to the same root.
+impl<T> Clone for PersistentVector<T> {
+    fn clone(&self) -> Self {
+        PersistentVector {
+            root: Rc::clone(&self.root),
+            len: self.len,
+            shift: self.shift,
+        }
+    }
+}
+
+impl<T: Clone> FromIterator<T> for PersistentVector<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        iter.into_iter()
+            .fold(PersistentVector::new(), |v, value| v.push_back(value))
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for PersistentVector<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn children<T>(v: &PersistentVector<T>) -> &Vec<Rc<Node<T>>> {
+        match &*v.root {
+            Node::Branch(children) => children,
+            Node::Leaf(_) => panic!("root is a leaf"),
+        }
+    }
+
+    #[test]
+    fn push_back_and_get() {
+        let v: PersistentVector<usize> = (0..2000).collect();
+
+        assert_eq!(v.len(), 2000);
+        assert!((0..2000).all(|i| v.get(i) == Some(&i)));
+        assert_eq!(v.get(2000), None);
+    }
+
+    #[test]
+    fn empty_vector_has_nothing_to_get_or_set() {
+        let v: PersistentVector<i32> = PersistentVector::new();
+
+        assert!(v.is_empty());
+        assert_eq!(v.get(0), None);
+        assert!(v.set(0, 1).is_none());
+    }
+
+    #[test]
+    fn set_leaves_old_version_unchanged() {
+        let v1: PersistentVector<i32> = (0..100).collect();
+        let v2 = v1.set(50, -1).unwrap();
+
+        assert_eq!(v1.get(50), Some(&50));
+        assert_eq!(v2.get(50), Some(&-1));
+        assert_eq!(v1.iter().filter(|&&x| x != 50).count(), 99);
+        assert_eq!(v2.iter().filter(|&&x| x >= 0).count(), 99);
+    }
+
+    #[test]
+    fn set_out_of_bounds_returns_none() {
+        let v: PersistentVector<i32> = (0..10).collect();
+
+        assert!(v.set(10, 0).is_none());
+    }
+
+    #[test]
+    fn set_only_copies_the_path_to_the_changed_leaf() {
+        // 100 values = 4 leaves under one branch.
+        let v1: PersistentVector<i32> = (0..100).collect();
+        let v2 = v1.set(0, -1).unwrap();
+
+        assert!(!Rc::ptr_eq(&children(&v1)[0], &children(&v2)[0]));
+        for (old, new) in children(&v1).iter().zip(children(&v2)).skip(1) {
+            assert!(Rc::ptr_eq(old, new));
+            // Referenced by v1's root and v2's root.
+            assert_eq!(Rc::strong_count(old), 2);
+        }
+        assert_eq!(Rc::strong_count(&children(&v1)[0]), 1);
+    }
+
+    #[test]
+    fn push_back_shares_full_leaves() {
+        let v1: PersistentVector<i32> = (0..64).collect();
+        let v2 = v1.push_back(64);
+
+        assert_eq!(children(&v2).len(), 3);
+        assert!(Rc::ptr_eq(&children(&v1)[0], &children(&v2)[0]));
+        assert!(Rc::ptr_eq(&children(&v1)[1], &children(&v2)[1]));
+
+        drop(v2);
+        assert_eq!(Rc::strong_count(&children(&v1)[0]), 1);
+    }
+
+    #[test]
+    fn push_back_past_a_full_trie_grows_a_new_root() {
+        let v1: PersistentVector<i32> = (0..32).collect();
+        let v2 = v1.push_back(32);
+
+        assert_eq!(v1.shift, 0);
+        assert_eq!(v2.shift, BITS);
+        assert!(Rc::ptr_eq(&v1.root, &children(&v2)[0]));
+        assert_eq!(Rc::strong_count(&v1.root), 2);
+        assert_eq!(v2.get(32), Some(&32));
+    }
+
+    #[test]
+    fn clone_shares_the_root() {
+        let v1: PersistentVector<i32> = (0..10).collect();
+        let v2 = v1.clone();
+
+        assert!(Rc::ptr_eq(&v1.root, &v2.root));
+        assert_eq!(Rc::strong_count(&v1.root), 2);
+    }
+}
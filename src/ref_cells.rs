@@ -0,0 +1,574 @@
+// The two cons lists from the ref_cells and ref_cells_original binaries. They only differ in
+// how a cell is wrapped, so they live side by side here and convert into each other.
+use std::cell::RefCell;
+use std::ops::Deref;
+use std::rc::Rc;
+
+use List::{Cons, Nil};
+
+// The list from ref_cells_original.rs, with the raw Rc<RefCell<i32>> cells.
+#[derive(Debug)]
+pub enum RawList {
+    Cons(Rc<RefCell<i32>>, Rc<RawList>),
+    Nil,
+}
+
+// My goal was to "alias" the Rc<RefCell<i32>> original node type into
+// something easier to understand and read.
+// Does wrapping Rc<RefCell<T>> in a tuple struct cause the reference counting
+// to break down? I.e., does Rc have to be the outermost type?
+#[derive(Debug)]
+pub enum List {
+    Cons(Node<i32>, Rc<List>),
+    Nil,
+}
+
+// Newtype pattern
+// https://doc.rust-lang.org/book/ch19-04-advanced-types.html
+#[derive(Debug)]
+pub struct Node<T>(pub Rc<RefCell<T>>);
+
+impl<T> Node<T> {
+    pub fn new(i: T) -> Node<T> {
+        Node(Rc::new(RefCell::new(i)))
+    }
+}
+
+// Is this the right implementation of clone? On second look, I think it is not because
+// we are only adding a reference to the inner data wrapped by this Node, but clone()
+// should return an entire new copy to the caller... I.e., yes we get a new Node wrapper, but
+// not a new clone of the underlying data. It's find with immutable data like here (with Rc),
+// but still.
+impl<T> Clone for Node<T> {
+    fn clone(&self) -> Self {
+        Node(Rc::clone(&self.0))
+    }
+}
+
+impl<T> Deref for Node<T> {
+    type Target = RefCell<T>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+// Copy-on-write alternative to Node. Node shares one RefCell, so `*node.borrow_mut() += 10`
+// changes the value for every list holding that node. CowNode shares a plain Rc<T> instead, and
+// make_mut() clones the value first if anyone else is holding it (so only this handle sees the
+// change) or mutates in place if this is the only handle.
+// https://doc.rust-lang.org/std/rc/struct.Rc.html#method.make_mut
+#[derive(Debug)]
+pub struct CowNode<T>(pub Rc<T>);
+
+impl<T> CowNode<T> {
+    pub fn new(i: T) -> CowNode<T> {
+        CowNode(Rc::new(i))
+    }
+}
+
+impl<T: Clone> CowNode<T> {
+    pub fn make_mut(&mut self) -> &mut T {
+        Rc::make_mut(&mut self.0)
+    }
+}
+
+impl<T> Clone for CowNode<T> {
+    fn clone(&self) -> Self {
+        CowNode(Rc::clone(&self.0))
+    }
+}
+
+impl<T> Deref for CowNode<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+// The same list as List, but built from CowNodes.
+#[derive(Debug)]
+pub enum CowList {
+    Cons(CowNode<i32>, Rc<CowList>),
+    Nil,
+}
+
+// Converting between the two representations only re-wraps each cell, so both lists end up
+// pointing at the same RefCells: a borrow_mut() through one is visible through the other.
+impl<T> From<Rc<RefCell<T>>> for Node<T> {
+    fn from(cell: Rc<RefCell<T>>) -> Self {
+        Node(cell)
+    }
+}
+
+impl<T> From<Node<T>> for Rc<RefCell<T>> {
+    fn from(node: Node<T>) -> Self {
+        node.0
+    }
+}
+
+// The spine is rebuilt iteratively (collect the cells, then cons from the back) so long lists
+// don't blow the stack the way a recursive conversion would.
+impl From<&RawList> for List {
+    fn from(list: &RawList) -> Self {
+        let mut cells = vec![];
+        let mut list = list;
+        while let RawList::Cons(cell, tail) = list {
+            cells.push(Rc::clone(cell));
+            list = tail;
+        }
+
+        let mut converted = Nil;
+        for cell in cells.into_iter().rev() {
+            converted = Cons(Node::from(cell), Rc::new(converted));
+        }
+
+        converted
+    }
+}
+
+impl From<RawList> for List {
+    fn from(list: RawList) -> Self {
+        List::from(&list)
+    }
+}
+
+impl From<&List> for RawList {
+    fn from(list: &List) -> Self {
+        let mut cells = vec![];
+        let mut list = list;
+        while let Cons(node, tail) = list {
+            cells.push(Rc::clone(&node.0));
+            list = tail;
+        }
+
+        let mut converted = RawList::Nil;
+        for cell in cells.into_iter().rev() {
+            converted = RawList::Cons(cell, Rc::new(converted));
+        }
+
+        converted
+    }
+}
+
+impl From<List> for RawList {
+    fn from(list: List) -> Self {
+        RawList::from(&list)
+    }
+}
+
+// A zipper splits the list at a "focus": everything before the focus is kept as a stack of
+// nodes (nearest on top), and the focus plus everything after it is still a plain Rc<List>.
+// Edits only touch the front of `right`, so the untouched tail stays shared with the
+// original list and into_list() only has to re-cons the nodes we walked past.
+// https://en.wikipedia.org/wiki/Zipper_(data_structure)
+#[derive(Debug)]
+pub struct Zipper {
+    left: Vec<Node<i32>>,
+    right: Rc<List>,
+}
+
+impl Zipper {
+    pub fn new(list: &Rc<List>) -> Zipper {
+        Zipper {
+            left: vec![],
+            right: Rc::clone(list),
+        }
+    }
+
+    // None when the focus has moved past the last element (which is where insert appends).
+    pub fn focus(&self) -> Option<&Node<i32>> {
+        match &*self.right {
+            Cons(node, _) => Some(node),
+            Nil => None,
+        }
+    }
+
+    pub fn left(&mut self) -> bool {
+        match self.left.pop() {
+            Some(node) => {
+                self.right = Rc::new(Cons(node, Rc::clone(&self.right)));
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn right(&mut self) -> bool {
+        let tail = match &*self.right {
+            Cons(node, tail) => {
+                self.left.push(Node::clone(node));
+                Rc::clone(tail)
+            }
+            Nil => return false,
+        };
+        self.right = tail;
+        true
+    }
+
+    // Swaps the node at the focus for a new one and hands back the old node. The old node is
+    // still shared by the original list, so mutating it through the returned handle would
+    // still be visible there.
+    pub fn replace(&mut self, node: Node<i32>) -> Option<Node<i32>> {
+        let (old, tail) = match &*self.right {
+            Cons(old, tail) => (Node::clone(old), Rc::clone(tail)),
+            Nil => return None,
+        };
+        self.right = Rc::new(Cons(node, tail));
+        Some(old)
+    }
+
+    // Inserts before the focus; the new node becomes the focus.
+    pub fn insert(&mut self, node: Node<i32>) {
+        self.right = Rc::new(Cons(node, Rc::clone(&self.right)));
+    }
+
+    // Removes the focus; the next node (if any) becomes the focus.
+    pub fn delete(&mut self) -> Option<Node<i32>> {
+        let (old, tail) = match &*self.right {
+            Cons(old, tail) => (Node::clone(old), Rc::clone(tail)),
+            Nil => return None,
+        };
+        self.right = tail;
+        Some(old)
+    }
+
+    pub fn into_list(self) -> Rc<List> {
+        self.left
+            .into_iter()
+            .rev()
+            .fold(self.right, |list, node| Rc::new(Cons(node, list)))
+    }
+}
+
+// The reference counts both binaries' main() print along the way, in order. Both lists give the
+// same counts, which is how we know wrapping the Rc<RefCell<i32>> in Node doesn't change the
+// reference counting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NarrativeCounts {
+    // a is referenced once by its variable and once by aa.
+    pub a: usize,
+    // aa is referenced by its variable, b, and c.
+    pub aa: usize,
+    // shared_node is referenced once by its variable, a, and aa.
+    pub shared_node: usize,
+    // Dropping b and c removes 2 references to aa. New total = 1.
+    pub aa_after_dropping_b_and_c: usize,
+    // Dropping `a` here doesn't affect the reference count of shared_node because the list that
+    // the variable `a` pointed to is still referenced by aa (a's ref count = 1 now) and therefore
+    // it's not dropped yet. Since `a` still ~exists, shared_node is still referenced by its own
+    // variable, the list that `a` used to point to, and the list that aa points to (still 3).
+    pub shared_node_after_dropping_a: usize,
+    // Dropping aa removes two references to the shared node: one from aa itself and one from
+    // the ghost of `a`. The ghost of a is cleaned up because its reference count moves to 0 when
+    // aa is dropped.
+    pub shared_node_after_dropping_aa: usize,
+}
+
+// The ref_cells_original narrative: two lists a and aa share a node, b and c share aa, and then
+// they're dropped one by one. `show` is handed each of a, aa, b and c after the shared node has
+// been changed through its RefCell.
+pub fn raw_narrative(mut show: impl FnMut(&str, &RawList)) -> NarrativeCounts {
+    let shared_node = Rc::new(RefCell::new(5));
+
+    let a = Rc::new(RawList::Cons(
+        Rc::clone(&shared_node),
+        Rc::new(RawList::Nil),
+    ));
+    let aa = Rc::new(RawList::Cons(Rc::clone(&shared_node), Rc::clone(&a)));
+
+    let b = RawList::Cons(Rc::new(RefCell::new(3)), Rc::clone(&aa));
+    let c = RawList::Cons(Rc::new(RefCell::new(4)), Rc::clone(&aa));
+
+    *shared_node.borrow_mut() += 10;
+
+    show("a", &a);
+    show("aa", &aa);
+    show("b", &b);
+    show("c", &c);
+
+    let (a_count, aa_count) = (Rc::strong_count(&a), Rc::strong_count(&aa));
+    let shared_node_count = Rc::strong_count(&shared_node);
+
+    drop(b);
+    drop(c);
+    let aa_after_dropping_b_and_c = Rc::strong_count(&aa);
+
+    drop(a);
+    let shared_node_after_dropping_a = Rc::strong_count(&shared_node);
+
+    drop(aa);
+    NarrativeCounts {
+        a: a_count,
+        aa: aa_count,
+        shared_node: shared_node_count,
+        aa_after_dropping_b_and_c,
+        shared_node_after_dropping_a,
+        shared_node_after_dropping_aa: Rc::strong_count(&shared_node),
+    }
+}
+
+// The same narrative with List and Node, from ref_cells.
+pub fn node_narrative(mut show: impl FnMut(&str, &List)) -> NarrativeCounts {
+    let shared_node = Node::new(5);
+
+    let a = Rc::new(Cons(Node::clone(&shared_node), Rc::new(Nil)));
+    let aa = Rc::new(Cons(Node::clone(&shared_node), Rc::clone(&a)));
+
+    let b = Cons(Node::new(3), Rc::clone(&aa));
+    let c = Cons(Node::new(4), Rc::clone(&aa));
+
+    *shared_node.borrow_mut() += 10;
+
+    show("a", &a);
+    show("aa", &aa);
+    show("b", &b);
+    show("c", &c);
+
+    let (a_count, aa_count) = (Rc::strong_count(&a), Rc::strong_count(&aa));
+    let shared_node_count = Rc::strong_count(&shared_node.0);
+
+    drop(b);
+    drop(c);
+    let aa_after_dropping_b_and_c = Rc::strong_count(&aa);
+
+    drop(a);
+    let shared_node_after_dropping_a = Rc::strong_count(&shared_node.0);
+
+    drop(aa);
+    NarrativeCounts {
+        a: a_count,
+        aa: aa_count,
+        shared_node: shared_node_count,
+        aa_after_dropping_b_and_c,
+        shared_node_after_dropping_a,
+        shared_node_after_dropping_aa: Rc::strong_count(&shared_node.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn from_values(values: &[i32]) -> Rc<List> {
+        values
+            .iter()
+            .rev()
+            .fold(Rc::new(Nil), |list, &i| Rc::new(Cons(Node::new(i), list)))
+    }
+
+    fn to_values(list: &List) -> Vec<i32> {
+        let mut values = vec![];
+        let mut list = list;
+        while let Cons(node, tail) = list {
+            values.push(*node.borrow());
+            list = tail;
+        }
+
+        values
+    }
+
+    // Returns the Rc that starts `n` nodes into the list.
+    fn nth_tail(list: &Rc<List>, n: usize) -> Rc<List> {
+        let mut list = Rc::clone(list);
+        for _ in 0..n {
+            let tail = match &*list {
+                Cons(_, tail) => Rc::clone(tail),
+                Nil => panic!("list is shorter than {n}"),
+            };
+            list = tail;
+        }
+
+        list
+    }
+
+    #[test]
+    fn zipper_without_edits_rebuilds_same_values() {
+        let list = from_values(&[1, 2, 3]);
+        let mut zipper = Zipper::new(&list);
+        while zipper.right() {}
+
+        assert_eq!(to_values(&zipper.into_list()), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn zipper_movement_stops_at_the_ends() {
+        let list = from_values(&[1, 2]);
+        let mut zipper = Zipper::new(&list);
+
+        assert!(!zipper.left());
+        assert!(zipper.right());
+        assert_eq!(*zipper.focus().unwrap().borrow(), 2);
+        assert!(zipper.right());
+        assert!(zipper.focus().is_none());
+        assert!(!zipper.right());
+        assert!(zipper.left());
+        assert_eq!(*zipper.focus().unwrap().borrow(), 2);
+    }
+
+    #[test]
+    fn zipper_replace_insert_and_delete() {
+        let list = from_values(&[1, 2, 3, 4]);
+        let mut zipper = Zipper::new(&list);
+        zipper.right();
+
+        assert_eq!(*zipper.replace(Node::new(20)).unwrap().borrow(), 2);
+        zipper.insert(Node::new(15));
+        assert_eq!(*zipper.focus().unwrap().borrow(), 15);
+        zipper.right();
+        zipper.right();
+        assert_eq!(*zipper.delete().unwrap().borrow(), 3);
+
+        assert_eq!(to_values(&zipper.into_list()), vec![1, 15, 20, 4]);
+        assert_eq!(to_values(&list), vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn zipper_insert_past_the_end_appends() {
+        let list = from_values(&[1]);
+        let mut zipper = Zipper::new(&list);
+        zipper.right();
+        zipper.insert(Node::new(2));
+
+        assert_eq!(to_values(&zipper.into_list()), vec![1, 2]);
+    }
+
+    #[test]
+    fn zipper_edits_on_empty_list_return_none() {
+        let mut zipper = Zipper::new(&Rc::new(Nil));
+
+        assert!(zipper.replace(Node::new(1)).is_none());
+        assert!(zipper.delete().is_none());
+    }
+
+    #[test]
+    fn zipper_into_list_shares_untouched_tail() {
+        let list = from_values(&[1, 2, 3, 4, 5]);
+        let mut zipper = Zipper::new(&list);
+        zipper.right();
+        zipper.replace(Node::new(20));
+
+        let edited = zipper.into_list();
+        let tail = nth_tail(&list, 2);
+
+        assert!(Rc::ptr_eq(&tail, &nth_tail(&edited, 2)));
+        // Referenced by the [2, ...] node of `list`, the [20, ...] node of `edited`, and `tail`.
+        assert_eq!(Rc::strong_count(&tail), 3);
+    }
+
+    #[test]
+    fn zipper_replaced_node_is_still_shared_with_original_list() {
+        let list = from_values(&[1, 2]);
+        let mut zipper = Zipper::new(&list);
+        zipper.right();
+
+        let old = zipper.replace(Node::new(20)).unwrap();
+        *old.borrow_mut() += 10;
+
+        assert_eq!(to_values(&list), vec![1, 12]);
+        assert_eq!(to_values(&zipper.into_list()), vec![1, 20]);
+    }
+
+    #[test]
+    fn raw_and_node_narratives_have_identical_counts() {
+        let raw = raw_narrative(|_, _| {});
+
+        assert_eq!(raw, node_narrative(|_, _| {}));
+        assert_eq!(
+            raw,
+            NarrativeCounts {
+                a: 2,
+                aa: 3,
+                shared_node: 3,
+                aa_after_dropping_b_and_c: 1,
+                shared_node_after_dropping_a: 3,
+                shared_node_after_dropping_aa: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn raw_list_converts_to_list_and_back() {
+        let raw = RawList::Cons(
+            Rc::new(RefCell::new(1)),
+            Rc::new(RawList::Cons(
+                Rc::new(RefCell::new(2)),
+                Rc::new(RawList::Nil),
+            )),
+        );
+        let list = List::from(&raw);
+        assert_eq!(to_values(&list), vec![1, 2]);
+
+        let back = RawList::from(list);
+        let (RawList::Cons(raw_cell, _), RawList::Cons(back_cell, _)) = (&raw, &back) else {
+            panic!("both lists should be non-empty");
+        };
+        assert!(Rc::ptr_eq(raw_cell, back_cell));
+    }
+
+    #[test]
+    fn converted_list_shares_cells_with_original() {
+        let shared_node = Rc::new(RefCell::new(5));
+        let raw = RawList::Cons(Rc::clone(&shared_node), Rc::new(RawList::Nil));
+
+        let list = List::from(&raw);
+        // Referenced by shared_node, raw, and list.
+        assert_eq!(Rc::strong_count(&shared_node), 3);
+
+        *shared_node.borrow_mut() += 10;
+        assert_eq!(to_values(&list), vec![15]);
+
+        drop(raw);
+        drop(list);
+        assert_eq!(Rc::strong_count(&shared_node), 1);
+    }
+
+    #[test]
+    fn empty_lists_convert() {
+        assert!(matches!(List::from(RawList::Nil), Nil));
+        assert!(matches!(RawList::from(Nil), RawList::Nil));
+    }
+
+    #[test]
+    fn cow_node_clones_when_shared() {
+        let mut shared_node = CowNode::new(5);
+        let list = CowList::Cons(CowNode::clone(&shared_node), Rc::new(CowList::Nil));
+        assert_eq!(Rc::strong_count(&shared_node.0), 2);
+
+        *shared_node.make_mut() += 10;
+
+        let CowList::Cons(list_node, _) = &list else {
+            panic!("list should be non-empty");
+        };
+        assert_eq!(*shared_node, 15);
+        assert_eq!(**list_node, 5);
+        // The variable now has its own copy, so neither Rc is shared any more.
+        assert!(!Rc::ptr_eq(&shared_node.0, &list_node.0));
+        assert_eq!(Rc::strong_count(&shared_node.0), 1);
+        assert_eq!(Rc::strong_count(&list_node.0), 1);
+    }
+
+    #[test]
+    fn cow_node_mutates_in_place_when_unique() {
+        let mut node = CowNode::new(5);
+        let before = Rc::as_ptr(&node.0);
+
+        *node.make_mut() += 10;
+
+        assert_eq!(*node, 15);
+        assert_eq!(Rc::as_ptr(&node.0), before);
+    }
+
+    #[test]
+    fn cow_node_mutates_in_place_after_other_handles_are_dropped() {
+        let mut shared_node = CowNode::new(5);
+        let list = CowList::Cons(CowNode::clone(&shared_node), Rc::new(CowList::Nil));
+        let before = Rc::as_ptr(&shared_node.0);
+
+        drop(list);
+        *shared_node.make_mut() += 10;
+
+        assert_eq!(Rc::as_ptr(&shared_node.0), before);
+    }
+}
@@ -0,0 +1,178 @@
+// A cache that doesn't keep its values alive. Callers get strong Node<T> handles, but the cache
+// itself only holds Weak references to the same cells, so once every caller drops its handle the
+// strong count hits 0, the value is freed, and the entry can no longer be upgraded.
+// https://doc.rust-lang.org/book/ch15-06-reference-cycles.html
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::rc::{Rc, Weak};
+
+use crate::ref_cells::Node;
+
+// Dead entries are only swept out when an insert finds the map has grown to this many entries
+// (or to twice the live entries left after the last sweep), so sweeping stays amortized O(1).
+const MIN_SWEEP_THRESHOLD: usize = 16;
+
+#[derive(Debug)]
+pub struct WeakCache<K, T> {
+    entries: HashMap<K, Weak<RefCell<T>>>,
+    sweep_threshold: usize,
+}
+
+impl<K: Eq + Hash, T> WeakCache<K, T> {
+    pub fn new() -> WeakCache<K, T> {
+        WeakCache {
+            entries: HashMap::new(),
+            sweep_threshold: MIN_SWEEP_THRESHOLD,
+        }
+    }
+
+    // None if the key was never cached or every handle to its value has been dropped.
+    pub fn get(&self, key: &K) -> Option<Node<T>> {
+        self.entries.get(key)?.upgrade().map(Node)
+    }
+
+    // Caches `value` under `key`, replacing whatever was there, and returns the only strong
+    // handle to it.
+    pub fn insert(&mut self, key: K, value: T) -> Node<T> {
+        let node = Node::new(value);
+        self.entries.insert(key, Rc::downgrade(&node.0));
+        self.sweep_if_needed();
+
+        node
+    }
+
+    pub fn get_or_insert_with<F: FnOnce() -> T>(&mut self, key: K, f: F) -> Node<T> {
+        match self.get(&key) {
+            Some(node) => node,
+            None => self.insert(key, f()),
+        }
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<Node<T>> {
+        self.entries.remove(key)?.upgrade().map(Node)
+    }
+
+    // Number of values that are still alive. Dead entries that haven't been swept yet aren't
+    // counted.
+    pub fn len(&self) -> usize {
+        self.entries
+            .values()
+            .filter(|weak| weak.strong_count() > 0)
+            .count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // Drops every entry whose value has been freed.
+    pub fn sweep(&mut self) {
+        self.entries.retain(|_, weak| weak.strong_count() > 0);
+        self.sweep_threshold = MIN_SWEEP_THRESHOLD.max(2 * self.entries.len());
+    }
+
+    fn sweep_if_needed(&mut self) {
+        if self.entries.len() >= self.sweep_threshold {
+            self.sweep();
+        }
+    }
+}
+
+impl<K: Eq + Hash, T> Default for WeakCache<K, T> {
+    fn default() -> Self {
+        WeakCache::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn get_returns_a_handle_to_the_same_cell() {
+        let mut cache = WeakCache::new();
+        let node = cache.insert("a", 5);
+
+        let again = cache.get(&"a").unwrap();
+        *again.borrow_mut() += 10;
+
+        assert_eq!(*node.borrow(), 15);
+        // node and again. The cache's Weak doesn't count.
+        assert_eq!(Rc::strong_count(&node.0), 2);
+        assert_eq!(Rc::weak_count(&node.0), 1);
+    }
+
+    #[test]
+    fn value_disappears_after_last_handle_is_dropped() {
+        let mut cache = WeakCache::new();
+        let node = cache.insert("a", 5);
+        let other = Node::clone(&node);
+
+        drop(node);
+        assert_eq!(*cache.get(&"a").unwrap().borrow(), 5);
+        assert_eq!(Rc::strong_count(&other.0), 1);
+
+        drop(other);
+        assert!(cache.get(&"a").is_none());
+        assert_eq!(cache.len(), 0);
+    }
+
+    #[test]
+    fn get_or_insert_with_reuses_live_values() {
+        let mut cache = WeakCache::new();
+        let first = cache.get_or_insert_with(1, || String::from("one"));
+        let second = cache.get_or_insert_with(1, || String::from("uno"));
+
+        assert!(Rc::ptr_eq(&first.0, &second.0));
+        assert_eq!(*second.borrow(), "one");
+
+        drop(first);
+        drop(second);
+        let third = cache.get_or_insert_with(1, || String::from("uno"));
+        assert_eq!(*third.borrow(), "uno");
+    }
+
+    #[test]
+    fn remove_returns_live_value_and_forgets_key() {
+        let mut cache = WeakCache::new();
+        let node = cache.insert("a", 5);
+
+        let removed = cache.remove(&"a").unwrap();
+        assert!(Rc::ptr_eq(&node.0, &removed.0));
+        assert!(cache.get(&"a").is_none());
+
+        drop(removed);
+        drop(node);
+        assert!(cache.remove(&"a").is_none());
+    }
+
+    #[test]
+    fn dead_entries_are_swept_lazily() {
+        let mut cache = WeakCache::new();
+        for i in 0..MIN_SWEEP_THRESHOLD - 1 {
+            drop(cache.insert(i, i));
+        }
+
+        // Nothing is alive, but nothing has been swept yet either.
+        assert_eq!(cache.len(), 0);
+        assert_eq!(cache.entries.len(), MIN_SWEEP_THRESHOLD - 1);
+
+        let kept = cache.insert(MIN_SWEEP_THRESHOLD, 0);
+        assert_eq!(cache.entries.len(), 1);
+        assert_eq!(cache.len(), 1);
+        assert_eq!(Rc::weak_count(&kept.0), 1);
+    }
+
+    #[test]
+    fn sweep_keeps_live_entries() {
+        let mut cache = WeakCache::new();
+        let kept = cache.insert("kept", 1);
+        drop(cache.insert("dropped", 2));
+
+        cache.sweep();
+
+        assert_eq!(cache.entries.len(), 1);
+        assert!(Rc::ptr_eq(&kept.0, &cache.get(&"kept").unwrap().0));
+    }
+}